@@ -0,0 +1,265 @@
+//! How the wumpus reacts to what the player does
+//!
+//! Each wumpus is moved by a `Behaviour`, which the engine asks at the three points where a
+//! wumpus might move: when the player walks into its room, when an arrow misses, and at the end
+//! of every turn. The built-in behaviours are the `Profile`s, which are part of the `Rules`; any
+//! other behaviour can be given to a game with `Game::set_behaviour`.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::maze::Room;
+use crate::rng::GameRng;
+use crate::rules::Rules;
+
+/// What a wumpus can see of the cave when deciding where to go
+#[derive(Copy, Clone, Debug)]
+pub struct Cave<'a> {
+    pub rules: &'a Rules,
+    pub rooms: &'a [Room],
+    /// The room the wumpus is in
+    pub wumpus: usize,
+    pub player: usize,
+    /// The number of turns that have been taken, including the current one
+    pub turn: u32,
+}
+
+/// Decides where a wumpus goes. Every method returns the room the wumpus should end up in, which
+/// is expected to be either the one it's in or one next to it.
+///
+/// All randomness must come from the `GameRng` that's passed in, so that games can be replayed.
+pub trait Behaviour: fmt::Debug + Send + Sync {
+    /// The player walked into the wumpus's room. Returning `None` means that it stays and eats
+    /// them.
+    fn woken(&self, cave: Cave, rng: &mut GameRng) -> Option<usize>;
+
+    /// An arrow missed, after flying through the given rooms
+    fn heard_miss(&self, cave: Cave, arrow: &[usize], rng: &mut GameRng) -> usize;
+
+    /// Called at the end of every turn that the player survives
+    fn turn_ended(&self, cave: Cave, _rng: &mut GameRng) -> usize {
+        cave.wumpus
+    }
+}
+
+/// The original rule: the wumpus sleeps until it's woken by the player walking in or by an
+/// arrow missing, when it moves to a random neighbouring room with the chance given by the rules
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Sleeper;
+
+/// Like `Sleeper`, but also moves to a random neighbouring room every `every` turns
+#[derive(Copy, Clone, Debug)]
+pub struct Wanderer {
+    pub every: u32,
+}
+
+/// Like `Sleeper`, except that a missed arrow always brings it one room closer to the player
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Hunter;
+
+/// Runs from arrows: whenever one misses, it moves to whichever neighbouring room is furthest
+/// from the arrow's path. It would also rather run than eat the player.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Coward;
+
+// A random room next to the wumpus
+fn random_neighbour(cave: Cave, rng: &mut GameRng) -> usize {
+    let idx = (rng.gen::<f32>() * 3.0) as usize;
+    cave.rooms[cave.wumpus][idx].0
+}
+
+// The number of tunnels between each room and the nearest of the `from` rooms
+fn distances(rooms: &[Room], from: &[usize]) -> Vec<usize> {
+    let mut dist = vec![usize::MAX; rooms.len()];
+    let mut queue = VecDeque::new();
+    for &r in from {
+        dist[r] = 0;
+        queue.push_back(r);
+    }
+
+    while let Some(r) = queue.pop_front() {
+        for &(next, _) in rooms[r].iter() {
+            if dist[next] == usize::MAX {
+                dist[next] = dist[r] + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+
+    dist
+}
+
+impl Behaviour for Sleeper {
+    fn woken(&self, cave: Cave, rng: &mut GameRng) -> Option<usize> {
+        // if we don't wake it (waking it moves it)
+        if rng.gen::<f32>() > cave.rules.wumpus_move_prob {
+            None
+        } else {
+            Some(random_neighbour(cave, rng))
+        }
+    }
+
+    fn heard_miss(&self, cave: Cave, _: &[usize], rng: &mut GameRng) -> usize {
+        if rng.gen::<f32>() < cave.rules.wumpus_move_prob {
+            random_neighbour(cave, rng)
+        } else {
+            cave.wumpus
+        }
+    }
+}
+
+impl Behaviour for Wanderer {
+    fn woken(&self, cave: Cave, rng: &mut GameRng) -> Option<usize> {
+        Sleeper.woken(cave, rng)
+    }
+
+    fn heard_miss(&self, cave: Cave, arrow: &[usize], rng: &mut GameRng) -> usize {
+        Sleeper.heard_miss(cave, arrow, rng)
+    }
+
+    fn turn_ended(&self, cave: Cave, rng: &mut GameRng) -> usize {
+        if self.every != 0 && cave.turn.is_multiple_of(self.every) {
+            random_neighbour(cave, rng)
+        } else {
+            cave.wumpus
+        }
+    }
+}
+
+impl Behaviour for Hunter {
+    fn woken(&self, cave: Cave, rng: &mut GameRng) -> Option<usize> {
+        Sleeper.woken(cave, rng)
+    }
+
+    fn heard_miss(&self, cave: Cave, _: &[usize], _: &mut GameRng) -> usize {
+        let dist = distances(cave.rooms, &[cave.player]);
+        let &(next, _) = cave.rooms[cave.wumpus]
+            .iter()
+            .min_by_key(|(r, _)| dist[*r])
+            .unwrap();
+
+        if dist[next] < dist[cave.wumpus] {
+            next
+        } else {
+            cave.wumpus
+        }
+    }
+}
+
+impl Behaviour for Coward {
+    fn woken(&self, cave: Cave, rng: &mut GameRng) -> Option<usize> {
+        Some(random_neighbour(cave, rng))
+    }
+
+    fn heard_miss(&self, cave: Cave, arrow: &[usize], _: &mut GameRng) -> usize {
+        let dist = distances(cave.rooms, arrow);
+        let &(next, _) = cave.rooms[cave.wumpus]
+            .iter()
+            .max_by_key(|(r, _)| dist[*r])
+            .unwrap();
+
+        if dist[next] > dist[cave.wumpus] {
+            next
+        } else {
+            cave.wumpus
+        }
+    }
+}
+
+/// The built-in behaviours, which can be chosen by name and stored with the rules. They're
+/// stored by the same names that `FromStr` accepts.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Profile {
+    #[default]
+    Sleeper,
+    Wanderer { every: u32 },
+    Hunter,
+    Coward,
+}
+
+impl Profile {
+    /// How often a wanderer moves, if it isn't given
+    pub const DEFAULT_WANDER: u32 = 5;
+
+    // Calls `f` with the behaviour that the profile stands for
+    fn with<T>(self, f: impl FnOnce(&dyn Behaviour) -> T) -> T {
+        match self {
+            Profile::Sleeper => f(&Sleeper),
+            Profile::Wanderer { every } => f(&Wanderer { every }),
+            Profile::Hunter => f(&Hunter),
+            Profile::Coward => f(&Coward),
+        }
+    }
+}
+
+impl Behaviour for Profile {
+    fn woken(&self, cave: Cave, rng: &mut GameRng) -> Option<usize> {
+        self.with(|b| b.woken(cave, rng))
+    }
+
+    fn heard_miss(&self, cave: Cave, arrow: &[usize], rng: &mut GameRng) -> usize {
+        self.with(|b| b.heard_miss(cave, arrow, rng))
+    }
+
+    fn turn_ended(&self, cave: Cave, rng: &mut GameRng) -> usize {
+        self.with(|b| b.turn_ended(cave, rng))
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Profile::Sleeper => write!(f, "sleeper"),
+            Profile::Wanderer { every } => write!(f, "wanderer:{}", every),
+            Profile::Hunter => write!(f, "hunter"),
+            Profile::Coward => write!(f, "coward"),
+        }
+    }
+}
+
+impl From<Profile> for String {
+    fn from(p: Profile) -> String {
+        p.to_string()
+    }
+}
+
+impl TryFrom<String> for Profile {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        s.parse()
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    /// Parses the name of a profile. A wanderer can be given how often it moves, as in
+    /// `wanderer:3`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let lower = s.to_lowercase();
+        let (name, every) = match lower.split_once(':') {
+            Some((name, n)) => match n.parse::<u32>() {
+                Ok(n) if n > 0 && name == "wanderer" => (name, n),
+                _ => return Err(format!("invalid wumpus behaviour '{}'", s)),
+            },
+            None => (lower.as_str(), Profile::DEFAULT_WANDER),
+        };
+
+        match name {
+            "sleeper" => Ok(Profile::Sleeper),
+            "wanderer" => Ok(Profile::Wanderer { every }),
+            "hunter" => Ok(Profile::Hunter),
+            "coward" => Ok(Profile::Coward),
+            _ => Err(format!(
+                "unknown wumpus behaviour '{}', expected sleeper, wanderer[:<turns>], hunter or coward",
+                s
+            )),
+        }
+    }
+}
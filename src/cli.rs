@@ -0,0 +1,426 @@
+//! The command-line arguments, and turning them into games
+
+use clap::{Args, Parser, Subcommand};
+use rand::SeedableRng;
+use std::path::Path;
+
+use wumpus::{ArrowCost, ExportFormat, Game, GameRng, Maze, Policy, Profile, Rules, Style};
+
+#[derive(Parser)]
+#[command(name = "wumpus", version, about = "Hunt the Wumpus, in randomly generated caves")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // Without a subcommand, everything is taken to be for 'play'
+    #[command(flatten)]
+    pub play: PlayArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Play a game (the default)
+    Play(PlayArgs),
+    /// Watch a game that was recorded with 'play --record'
+    Replay(ReplayArgs),
+    /// Write out a whole cave as Graphviz DOT, JSON or SVG, without playing it
+    Export(ExportArgs),
+    /// Let the computer play, explaining each move
+    Solve(SolveArgs),
+    /// Play lots of games automatically, to see how winnable each cave size is
+    Simulate(SimulateArgs),
+    /// List the high scores
+    Scores(ScoresArgs),
+    /// Show your win rate and other totals from every game you've finished
+    Stats,
+    /// Play today's cave, which is the same for everyone
+    Daily(DailyArgs),
+}
+
+/// Everything that decides what a new cave looks like
+#[derive(Args)]
+pub struct CaveArgs {
+    /// The number of rooms in the cave, which must be even and at least 4
+    #[arg(long, value_parser = parse_rooms)]
+    pub rooms: Option<u32>,
+
+    // The number of rooms can also be given on its own, as it was before there were flags
+    #[arg(value_name = "ROOMS", value_parser = parse_rooms, conflicts_with = "rooms", hide = true)]
+    pub rooms_arg: Option<u32>,
+
+    /// Play in a hand-made cave from a level file, instead of a generated one
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["rooms", "rooms_arg", "classic", "bats", "pits", "wumpuses"]
+    )]
+    pub level: Option<String>,
+
+    /// The seed for the cave and every other random choice in the game
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[command(flatten)]
+    pub rules: RulesArgs,
+}
+
+/// The rules, and the flags that change them
+#[derive(Args)]
+pub struct RulesArgs {
+    /// A rules file, or one of the presets: easy, normal, classic, hard or nightmare
+    #[arg(long, value_name = "PRESET|FILE")]
+    pub rules: Option<String>,
+
+    /// Play in the cave from the original 1973 game, by its rules (the same as --rules classic)
+    #[arg(long, conflicts_with = "rules")]
+    pub classic: bool,
+
+    /// The number of rooms with bats
+    #[arg(long, value_name = "N")]
+    pub bats: Option<usize>,
+
+    /// The number of bottomless pits
+    #[arg(long, value_name = "N")]
+    pub pits: Option<usize>,
+
+    /// The number of wumpuses
+    #[arg(long, value_name = "N")]
+    pub wumpuses: Option<usize>,
+
+    /// Whether shots cost an arrow for each room they go through, or one arrow each
+    #[arg(long, value_name = "room|shot", value_parser = parse_arrow_cost)]
+    pub arrow_cost: Option<ArrowCost>,
+
+    /// Leave arrows that miss where they land, so that they can be picked up again
+    #[arg(long)]
+    pub recover_arrows: bool,
+
+    /// How the wumpus moves: sleeper, wanderer[:<turns>], hunter or coward
+    #[arg(long, value_name = "BEHAVIOUR")]
+    pub wumpus: Option<Profile>,
+}
+
+#[derive(Args)]
+pub struct PlayArgs {
+    #[command(flatten)]
+    pub cave: CaveArgs,
+
+    /// Carry on with a saved game, instead of starting a new one
+    #[arg(long, value_name = "FILE")]
+    pub load: Option<String>,
+
+    /// Record every turn to a file, to watch later with 'replay'
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<String>,
+
+    /// Mark rooms automatically from the warnings you've been given
+    #[arg(long)]
+    pub auto_mark: bool,
+
+    /// Play full-screen, using the arrow keys
+    #[arg(long)]
+    pub tui: bool,
+
+    /// How to draw the cave: ascii, unicode or color. By default, this depends on the terminal.
+    #[arg(long)]
+    pub style: Option<Style>,
+
+    /// Allow turns to be taken back with 'undo' and 'rewind <turns>' (or 'u' with --tui). The game
+    /// won't go in the high score table if any are.
+    #[arg(long)]
+    pub practice: bool,
+}
+
+#[derive(Args)]
+pub struct DailyArgs {
+    /// Mark rooms automatically from the warnings you've been given
+    #[arg(long)]
+    pub auto_mark: bool,
+
+    /// Play full-screen, using the arrow keys
+    #[arg(long)]
+    pub tui: bool,
+
+    /// How to draw the cave: ascii, unicode or color. By default, this depends on the terminal.
+    #[arg(long)]
+    pub style: Option<Style>,
+}
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    /// The recorded game
+    pub file: String,
+
+    /// The number of seconds between turns
+    #[arg(long, value_name = "SECONDS", default_value_t = 1.0, value_parser = parse_speed)]
+    pub speed: f64,
+
+    /// Start paused
+    #[arg(long)]
+    pub paused: bool,
+
+    /// How to draw the cave: ascii, unicode or color
+    #[arg(long)]
+    pub style: Option<Style>,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub cave: CaveArgs,
+
+    /// The format to write: dot, json or svg
+    #[arg(long, default_value_t = ExportFormat::Json)]
+    pub format: ExportFormat,
+
+    /// Export the cave from a saved game, instead of generating one
+    #[arg(long, value_name = "FILE")]
+    pub load: Option<String>,
+
+    /// The file to write to, instead of stdout
+    #[arg(long, short, value_name = "FILE")]
+    pub output: Option<String>,
+}
+
+#[derive(Args)]
+pub struct SolveArgs {
+    #[command(flatten)]
+    pub cave: CaveArgs,
+
+    /// Play this many games quietly, and report how many were won. Each game after the first uses
+    /// the next seed along.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = parse_games)]
+    pub games: u32,
+}
+
+#[derive(Args)]
+pub struct SimulateArgs {
+    /// The cave sizes to try, as FIRST..LAST. The caves are always generated, even with the
+    /// classic rules.
+    #[arg(long, value_name = "FIRST..LAST", value_parser = parse_room_range)]
+    pub rooms: (u32, u32),
+
+    /// How far apart the cave sizes are, which must be even
+    #[arg(long, value_name = "N", default_value_t = 20, value_parser = parse_step)]
+    pub step: u32,
+
+    /// The number of games to play for each cave size
+    #[arg(long, value_name = "N", default_value_t = 1000, value_parser = parse_games)]
+    pub games: u32,
+
+    /// How to play: random, cautious or solver
+    #[arg(long, default_value_t = Policy::Solver)]
+    pub policy: Policy,
+
+    /// The seed for the first game. The same seeds are used for every cave size.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Write the results as CSV, instead of a table
+    #[arg(long)]
+    pub csv: bool,
+
+    #[command(flatten)]
+    pub rules: RulesArgs,
+}
+
+#[derive(Args)]
+pub struct ScoresArgs {
+    /// Only list the scores for these rules: a rules file, or the name of a preset or of some
+    /// custom rules
+    #[arg(long, value_name = "NAME|FILE")]
+    pub rules: Option<String>,
+
+    /// Only list the scores for caves with this many rooms
+    #[arg(long, value_parser = parse_rooms)]
+    pub rooms: Option<u32>,
+}
+
+fn parse_rooms(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(n) if n >= 4 && n % 2 == 0 => Ok(n),
+        _ => Err("the number of rooms must be even and at least 4".into()),
+    }
+}
+
+fn parse_room_range(s: &str) -> Result<(u32, u32), String> {
+    let (first, last) = match s.split_once("..") {
+        Some((first, last)) => (parse_rooms(first)?, parse_rooms(last)?),
+        None => (parse_rooms(s)?, parse_rooms(s)?),
+    };
+
+    if first <= last {
+        Ok((first, last))
+    } else {
+        Err("the first number of rooms can't be more than the last".into())
+    }
+}
+
+fn parse_step(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(n) if n > 0 && n % 2 == 0 => Ok(n),
+        _ => Err("the step must be even and more than 0".into()),
+    }
+}
+
+fn parse_games(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err("expected a number of games, at least 1".into()),
+    }
+}
+
+fn parse_arrow_cost(s: &str) -> Result<ArrowCost, String> {
+    match s {
+        "room" => Ok(ArrowCost::PerRoom),
+        "shot" => Ok(ArrowCost::PerShot),
+        _ => Err("expected 'room' or 'shot'".into()),
+    }
+}
+
+fn parse_speed(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(secs) if secs >= 0.0 && secs.is_finite() => Ok(secs),
+        _ => Err("expected a number of seconds".into()),
+    }
+}
+
+// Gives one of the preset rules by name, or otherwise reads the rules from the file at `arg`
+fn load_rules(arg: &str) -> Result<Rules, String> {
+    if let Some(rules) = Rules::preset(arg) {
+        return Ok(rules);
+    }
+
+    let text = std::fs::read_to_string(arg).map_err(|e| {
+        format!(
+            "'{}' isn't one of the preset rules ({}), and can't be read as a file: {}",
+            arg,
+            Rules::PRESETS.join(", "),
+            e
+        )
+    })?;
+    Rules::from_toml(&text).map_err(|e| format!("failed to load '{}': {}", arg, e))
+}
+
+impl CaveArgs {
+    /// The number of rooms, however it was given
+    pub fn n_rooms(&self) -> Option<u32> {
+        self.rooms.or(self.rooms_arg)
+    }
+
+    /// Returns whether any of the arguments change the rules (or the cave)
+    pub fn changes_rules(&self) -> bool {
+        self.level.is_some() || self.rules.changes_rules()
+    }
+
+    /// The seed that was given, or otherwise a random one. A seed is always picked up front, so
+    /// that any game can be replayed.
+    pub fn seed(&self) -> u64 {
+        self.seed.unwrap_or_else(|| GameRng::from_entropy().state())
+    }
+
+    /// Starts a new game, returning it along with its seed
+    pub fn new_game(&self) -> Result<(Game, u64), String> {
+        let seed = self.seed();
+        Ok((self.game_from_seed(seed)?, seed))
+    }
+
+    /// Starts a new game with the given seed, ignoring '--seed'
+    pub fn game_from_seed(&self, seed: u64) -> Result<Game, String> {
+        let mut rules = self.rules.rules()?;
+
+        if let Some(path) = &self.level {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| format!("failed to read '{}': {}", path, e))?;
+            let maze = Maze::from_level(&text, rules.starting_arrows)
+                .map_err(|e| format!("failed to load '{}': {}", path, e))?;
+
+            // The rules are stored with the game, so they should agree with the level
+            rules.classic_cave = false;
+            rules.hazards = maze.hazards();
+            rules.starting_arrows = maze.arrows;
+            return Ok(Game::with_rules(maze, rules, GameRng::from_seed(seed)));
+        }
+
+        let n_rooms = match self.n_rooms() {
+            Some(_) if rules.classic_cave => {
+                return Err("the classic cave always has 20 rooms".into())
+            }
+            None if rules.classic_cave => 20,
+            Some(n) => n,
+            None => return Err("you must give the number of rooms, with --rooms".into()),
+        };
+
+        if !rules.fit(n_rooms as usize) {
+            return Err(format!(
+                "there isn't enough room for all of the hazards in {} rooms",
+                n_rooms
+            ));
+        }
+
+        let adds = (n_rooms - 4) / 2;
+        Ok(Game::from_seed(adds, rules, seed))
+    }
+}
+
+impl SimulateArgs {
+    /// The seed for the first game, or a random one if none was given
+    pub fn seed(&self) -> u64 {
+        self.seed.unwrap_or_else(|| GameRng::from_entropy().state())
+    }
+}
+
+impl ScoresArgs {
+    /// The name of the rules to list scores for, if they were given
+    pub fn rules_name(&self) -> Result<Option<String>, String> {
+        match &self.rules {
+            Some(arg) if Path::new(arg).is_file() => load_rules(arg).map(|r| Some(r.name())),
+            other => Ok(other.clone()),
+        }
+    }
+}
+
+impl RulesArgs {
+    /// Returns whether any of the arguments change the rules
+    pub fn changes_rules(&self) -> bool {
+        self.rules.is_some()
+            || self.classic
+            || self.bats.is_some()
+            || self.pits.is_some()
+            || self.wumpuses.is_some()
+            || self.arrow_cost.is_some()
+            || self.recover_arrows
+            || self.wumpus.is_some()
+    }
+
+    /// Gives the rules, starting from '--rules' (or '--classic') and then changed by the other
+    /// flags
+    pub fn rules(&self) -> Result<Rules, String> {
+        let mut rules = match &self.rules {
+            Some(r) => load_rules(r)?,
+            None if self.classic => Rules::classic(),
+            None => Rules::default(),
+        };
+
+        if let Some(n) = self.bats {
+            rules.hazards.bats = n;
+        }
+        if let Some(n) = self.pits {
+            rules.hazards.pits = n;
+        }
+        if let Some(n) = self.wumpuses {
+            rules.hazards.wumpuses = n;
+        }
+        if let Some(cost) = self.arrow_cost {
+            rules.arrow_cost = cost;
+        }
+        if let Some(profile) = self.wumpus {
+            rules.wumpus = profile;
+        }
+        rules.recover_arrows |= self.recover_arrows;
+
+        rules.check().map_err(|e| e.to_string())?;
+        Ok(rules)
+    }
+}
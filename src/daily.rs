@@ -0,0 +1,219 @@
+//! The daily challenge: one cave a day, the same for everyone
+//!
+//! The day's cave comes from a seed made from the date, and is always played with the same rules
+//! and number of rooms, so anyone playing on the same (UTC) day gets exactly the same game.
+
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::{Action, Event, Game};
+use crate::replay::Turn;
+use crate::rng::GameRng;
+use crate::rules::Rules;
+use crate::save::{read_versioned, write_versioned, SaveError};
+use crate::simulate::Outcome;
+
+/// The rules and cave size used for every daily challenge. Changing these changes everyone's
+/// cave, so they should stay put.
+pub const DAILY_RULES: &str = "hard";
+pub const DAILY_ROOMS: usize = 30;
+
+/// The current version of the file of daily results, which is separate from `SAVE_VERSION`
+pub const DAILY_VERSION: u32 = 1;
+
+// Mixed into the date, so that the daily seeds aren't just small numbers that someone might
+// also use with --seed
+const DAILY_SEED: u64 = 0x6461_696c_7977_6d70;
+
+// The number of turns on each line of the shared path
+const PATH_WIDTH: usize = 10;
+
+/// A day in the Gregorian calendar
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+/// How a day's challenge went
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub outcome: Outcome,
+    pub turns: u32,
+    /// The text to share, from `share`
+    pub share: String,
+}
+
+/// The result of every daily challenge that's been played, by date
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyLog {
+    results: BTreeMap<String, DailyResult>,
+}
+
+impl Date {
+    /// Today's date in UTC, which is the same for everyone at any moment
+    pub fn today() -> Date {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        Date::from_days((secs / 86400) as i64)
+    }
+
+    /// The date a number of days after 1970-01-01
+    pub fn from_days(days: i64) -> Date {
+        // From Howard Hinnant's `civil_from_days`, which counts in 400-year eras starting on
+        // 0000-03-01, so that leap days come at the end of each year
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + (month <= 2) as i64) as i32;
+
+        Date { year, month, day }
+    }
+
+    /// The number of days since 1970-01-01
+    pub fn days(self) -> i64 {
+        let year = self.year as i64 - (self.month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let mp = (self.month as i64 + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+        era * 146_097 + doe - 719_468
+    }
+}
+
+/// The seed for the given day's cave
+pub fn daily_seed(date: Date) -> u64 {
+    GameRng::from_seed(date.days() as u64 ^ DAILY_SEED).next_u64()
+}
+
+/// The rules for every daily challenge
+pub fn daily_rules() -> Rules {
+    Rules::preset(DAILY_RULES).expect("the daily rules are one of the presets")
+}
+
+/// Starts the given day's challenge
+pub fn daily_game(date: Date) -> Game {
+    let adds = (DAILY_ROOMS as u32 - 4) / 2;
+    Game::from_seed(adds, daily_rules(), daily_seed(date))
+}
+
+/// The result of a day's challenge as text to share, with a row of symbols standing for each
+/// turn that doesn't give away anything about the cave: which rooms were visited, or which way
+/// the player went
+pub fn share(date: Date, game: &Game, turns: &[Turn]) -> String {
+    let result = match Outcome::of(game) {
+        Some(Outcome::Won) => format!("won in {} turns", game.turn()),
+        Some(Outcome::FellInPit) => format!("fell into a pit after {} turns", game.turn()),
+        Some(Outcome::Eaten) => format!("eaten by a wumpus after {} turns", game.turn()),
+        Some(Outcome::OutOfArrows) => format!("ran out of arrows after {} turns", game.turn()),
+        Some(Outcome::ShotYourself) => format!("shot myself after {} turns", game.turn()),
+        Some(Outcome::GaveUp) | None => format!("gave up after {} turns", game.turn()),
+    };
+
+    let mut out = format!("Hunt the Wumpus, daily cave {}: {}", date, result);
+    for (i, turn) in turns.iter().enumerate() {
+        out.push(if i % PATH_WIDTH == 0 { '\n' } else { ' ' });
+        out.push_str(symbol(turn));
+    }
+    out
+}
+
+// The symbol for a single turn in the shared path
+fn symbol(turn: &Turn) -> &'static str {
+    let has = |event: &Event| turn.events.contains(event);
+    let shot = matches!(turn.action, Action::Shoot(_) | Action::ShootRooms(_));
+
+    if has(&Event::WumpusKilled) {
+        "🏆"
+    } else if has(&Event::FellInPit) {
+        "🕳️"
+    } else if has(&Event::EatenByWumpus) {
+        "💀"
+    } else if has(&Event::ShotYourself) || has(&Event::ShotYourselfAndWumpus) {
+        "💥"
+    } else if has(&Event::OutOfArrows) {
+        "❌"
+    } else if shot && turn.events.iter().any(|e| matches!(e, Event::KilledAWumpus { .. })) {
+        "🎯"
+    } else if shot {
+        "🏹"
+    } else if turn.events.iter().any(|e| matches!(e, Event::BatsCarriedYou { .. })) {
+        "🦇"
+    } else if [Event::SmellWumpus, Event::HearBats, Event::FeelWind].iter().any(has) {
+        "🟨"
+    } else {
+        "🟩"
+    }
+}
+
+impl DailyLog {
+    pub fn save<W: Write>(&self, writer: W) -> Result<(), SaveError> {
+        write_versioned(writer, DAILY_VERSION, self)
+    }
+
+    pub fn load<R: Read>(reader: R) -> Result<DailyLog, SaveError> {
+        read_versioned(reader, DAILY_VERSION)
+    }
+
+    /// The result for the given day, if it's been played
+    pub fn get(&self, date: Date) -> Option<&DailyResult> {
+        self.results.get(&date.to_string())
+    }
+
+    /// Records the result for the given day, replacing any earlier one
+    pub fn set(&mut self, date: Date, result: DailyResult) {
+        self.results.insert(date.to_string(), result);
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Date {
+        Date { year, month, day }
+    }
+
+    #[test]
+    fn known_dates() {
+        assert_eq!(Date::from_days(0), date(1970, 1, 1));
+        assert_eq!(Date::from_days(-1), date(1969, 12, 31));
+        assert_eq!(date(2000, 2, 29).days(), 11_016);
+        assert_eq!(date(2000, 3, 1).days(), 11_017);
+        assert_eq!(date(2026, 10, 18).to_string(), "2026-10-18");
+    }
+
+    #[test]
+    fn days_round_trip() {
+        for days in -800_000..800_000 {
+            let d = Date::from_days(days);
+            assert_eq!(d.days(), days, "{}", d);
+        }
+    }
+
+    #[test]
+    fn different_days_get_different_caves() {
+        let today = date(2026, 10, 18);
+        let tomorrow = Date::from_days(today.days() + 1);
+        assert_eq!(tomorrow, date(2026, 10, 19));
+        assert_eq!(daily_seed(today), daily_seed(date(2026, 10, 18)));
+        assert_ne!(daily_seed(today), daily_seed(tomorrow));
+    }
+}
@@ -0,0 +1,243 @@
+//! Working out where the hazards might be, using only what the player has been told
+//!
+//! This never looks at where the hazards actually are: everything here comes from the rooms the
+//! player has been in and the warnings they got there.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::game::Event;
+use crate::maze::Room;
+
+/// The kinds of hazard that the player can be warned about
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Hazard {
+    Pit,
+    Bats,
+    Wumpus,
+}
+
+/// What's known about whether a particular hazard is in a particular room
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The hazard definitely isn't there
+    Absent,
+    /// Nothing is known either way
+    Unknown,
+    /// A neighbouring room gave a warning, so the hazard might be there
+    Suspected,
+    /// The hazard must be there
+    Present,
+}
+
+/// Everything that can be deduced from the warnings the player has received
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Knowledge {
+    // For each hazard, the rooms it's been ruled out of, and the rooms that gave a warning about
+    // it. These are indexed by `Hazard as usize`.
+    absent: [BTreeSet<usize>; 3],
+    warned: [BTreeSet<usize>; 3],
+    // Rooms that are known to have bats, because the player was carried off from them
+    bats: BTreeSet<usize>,
+}
+
+impl Hazard {
+    pub const ALL: [Hazard; 3] = [Hazard::Pit, Hazard::Bats, Hazard::Wumpus];
+
+    // The warning given when this hazard is in a neighbouring room
+    fn warning(self) -> Event {
+        match self {
+            Hazard::Pit => Event::FeelWind,
+            Hazard::Bats => Event::HearBats,
+            Hazard::Wumpus => Event::SmellWumpus,
+        }
+    }
+}
+
+impl Knowledge {
+    /// Records that the player is safely in `room`, and received the given warnings there. Any
+    /// events other than warnings are ignored.
+    pub fn observe(&mut self, rooms: &[Room], room: usize, events: &[Event]) {
+        for &h in Hazard::ALL.iter() {
+            self.absent[h as usize].insert(room);
+
+            if events.contains(&h.warning()) {
+                self.warned[h as usize].insert(room);
+            } else {
+                self.warned[h as usize].remove(&room);
+                for &(next, _) in rooms[room].iter() {
+                    self.absent[h as usize].insert(next);
+                }
+            }
+        }
+    }
+
+    /// Records that the player was carried off by bats from `room`
+    pub fn found_bats(&mut self, room: usize) {
+        self.bats.insert(room);
+    }
+
+    /// Forgets everything about where the wumpus is, because it might have moved
+    pub fn wumpus_moved(&mut self) {
+        self.absent[Hazard::Wumpus as usize].clear();
+        self.warned[Hazard::Wumpus as usize].clear();
+    }
+
+    /// Gives what's known about whether the hazard is in the room
+    pub fn status(&self, rooms: &[Room], room: usize, hazard: Hazard) -> Status {
+        let absent = &self.absent[hazard as usize];
+
+        if hazard == Hazard::Bats && self.bats.contains(&room) {
+            return Status::Present;
+        } else if absent.contains(&room) {
+            return Status::Absent;
+        }
+
+        let mut suspected = false;
+        for &w in self.warned[hazard as usize].iter() {
+            let candidates: Vec<usize> = rooms[w]
+                .iter()
+                .map(|&(r, _)| r)
+                .filter(|r| !absent.contains(r))
+                .collect();
+
+            if candidates.contains(&room) {
+                // If this is the only place the warning could have come from, the hazard has to
+                // be here.
+                if candidates.len() == 1 {
+                    return Status::Present;
+                }
+                suspected = true;
+            }
+        }
+
+        if suspected {
+            Status::Suspected
+        } else {
+            Status::Unknown
+        }
+    }
+
+    /// Gives a rough chance that the hazard is in the room: for every warning it could explain,
+    /// one over the number of rooms that warning could have come from, taking the highest. Rooms
+    /// that no warning says anything about are given `unknown`.
+    pub fn chance(&self, rooms: &[Room], room: usize, hazard: Hazard, unknown: f32) -> f32 {
+        match self.status(rooms, room, hazard) {
+            Status::Absent => return 0.0,
+            Status::Present => return 1.0,
+            Status::Unknown => return unknown,
+            Status::Suspected => (),
+        }
+
+        let absent = &self.absent[hazard as usize];
+        self.warned[hazard as usize]
+            .iter()
+            .filter_map(|&w| {
+                let candidates = rooms[w].iter().filter(|(r, _)| !absent.contains(r));
+                let (count, here) = candidates.fold((0, false), |(n, here), &(r, _)| {
+                    (n + 1, here || r == room)
+                });
+                if here {
+                    Some(1.0 / count as f32)
+                } else {
+                    None
+                }
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// Returns whether every hazard has been ruled out of the room
+    pub fn is_safe(&self, rooms: &[Room], room: usize) -> bool {
+        Hazard::ALL
+            .iter()
+            .all(|&h| self.status(rooms, room, h) == Status::Absent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Direction::{East, North, South};
+
+    // A triangular prism: rooms 0, 1 and 2 form one end and 3, 4 and 5 the other, with a tunnel
+    // between each room and the one opposite it
+    fn prism() -> Vec<Room> {
+        let room = |a, b, c| [(a, North), (b, East), (c, South)];
+        vec![
+            room(1, 2, 3),
+            room(0, 2, 4),
+            room(0, 1, 5),
+            room(0, 4, 5),
+            room(1, 3, 5),
+            room(2, 3, 4),
+        ]
+    }
+
+    #[test]
+    fn no_warning_rules_out_the_neighbours() {
+        let rooms = prism();
+        let mut k = Knowledge::default();
+        k.observe(&rooms, 0, &[]);
+
+        for r in 0..4 {
+            assert!(k.is_safe(&rooms, r), "room {}", r);
+        }
+        assert_eq!(k.status(&rooms, 4, Hazard::Pit), Status::Unknown);
+        assert_eq!(k.chance(&rooms, 4, Hazard::Pit, 0.2), 0.2);
+    }
+
+    #[test]
+    fn warning_makes_the_neighbours_suspects() {
+        let rooms = prism();
+        let mut k = Knowledge::default();
+        k.observe(&rooms, 0, &[Event::SmellWumpus]);
+
+        assert_eq!(k.status(&rooms, 0, Hazard::Wumpus), Status::Absent);
+        for r in 1..4 {
+            assert_eq!(k.status(&rooms, r, Hazard::Wumpus), Status::Suspected);
+            assert_eq!(k.chance(&rooms, r, Hazard::Wumpus, 0.0), 1.0 / 3.0);
+            assert_eq!(k.status(&rooms, r, Hazard::Pit), Status::Absent);
+        }
+        assert_eq!(k.status(&rooms, 4, Hazard::Wumpus), Status::Unknown);
+    }
+
+    #[test]
+    fn one_room_left_must_have_the_hazard() {
+        let rooms = prism();
+        let mut k = Knowledge::default();
+        k.observe(&rooms, 0, &[Event::FeelWind]);
+        k.observe(&rooms, 1, &[]);
+
+        // Being in room 1 rules out 1 and 2, leaving 3 as the only source of the wind
+        assert_eq!(k.status(&rooms, 2, Hazard::Pit), Status::Absent);
+        assert_eq!(k.status(&rooms, 3, Hazard::Pit), Status::Present);
+        assert_eq!(k.chance(&rooms, 3, Hazard::Pit, 0.0), 1.0);
+    }
+
+    #[test]
+    fn bats_are_known_once_they_carry_the_player() {
+        let rooms = prism();
+        let mut k = Knowledge::default();
+        k.found_bats(4);
+
+        assert_eq!(k.status(&rooms, 4, Hazard::Bats), Status::Present);
+        assert_eq!(k.status(&rooms, 4, Hazard::Pit), Status::Unknown);
+        assert!(!k.is_safe(&rooms, 4));
+    }
+
+    #[test]
+    fn moving_wumpus_is_forgotten() {
+        let rooms = prism();
+        let mut k = Knowledge::default();
+        k.observe(&rooms, 0, &[Event::SmellWumpus]);
+        k.observe(&rooms, 1, &[]);
+        assert_eq!(k.status(&rooms, 3, Hazard::Wumpus), Status::Present);
+
+        k.wumpus_moved();
+        for r in 0..rooms.len() {
+            assert_eq!(k.status(&rooms, r, Hazard::Wumpus), Status::Unknown, "room {}", r);
+        }
+        // Nothing else moves
+        assert_eq!(k.status(&rooms, 2, Hazard::Pit), Status::Absent);
+    }
+}
@@ -0,0 +1,354 @@
+use crate::maze::{Maze, Room};
+use crate::render::{Highlight, Renderer, Unicode};
+use crate::Direction::{East, North, South, West};
+
+/// Extra things to show for a room when it's drawn
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    /// Shown under the room number. Labels are cut off at the width of the room, and aren't shown
+    /// for the center room.
+    pub label: String,
+    /// The highlight for the room number
+    pub highlight: Highlight,
+    pub label_highlight: Highlight,
+}
+
+impl Default for Annotation {
+    fn default() -> Self {
+        Annotation {
+            label: String::new(),
+            highlight: Highlight::None,
+            label_highlight: Highlight::None,
+        }
+    }
+}
+
+impl Maze {
+    pub fn display_room(&self, room_idx: usize) -> String {
+        self.render_room(room_idx, &Unicode, |_| Annotation::default())
+    }
+
+    // Like `display_room`, but with a choice of renderer and extra annotations for each room,
+    // including the center one
+    pub fn render_room<F>(&self, room_idx: usize, renderer: &dyn Renderer, annotate: F) -> String
+    where
+        F: Fn(usize) -> Annotation,
+    {
+        // Typical display, with terminal border at the indent of the first '/'
+        /*
+                          .
+                      ╔═╝ . ╚═╗
+                      ╝       ║
+                     ..   7   ║
+                      ╗       ║
+              .       ╚═╗   ╔═╝       .
+          ╔═╝ . ╚═╗  ╔══╝   ╚══╗  ╔═╝ . ╚═╗
+          ╝       ╚══╝         ╚══╝       ║
+         ..  37          You         61   ║
+          ╗       ╔══╗    3    ╔══╗       ║
+          ╚═══════╝  ╚═════════╝  ╚═╗ . ╔═╝
+                                      .
+        */
+        //
+        // Everything is sized from the inside width of the neighbouring rooms - 7 in the picture
+        // above. The center room is two wider, and everything else follows from that.
+
+        // the maximum length of the room numbers
+        let max_length = self.rooms[room_idx].iter()
+            .map(|(i,_)| *i)
+            .chain(std::iter::once(room_idx))
+            .map(|i| i.to_string().len())
+            .max()
+            .unwrap();
+
+        // The inside width needs to be odd, so that the tunnels can be centered. Room numbers get
+        // at least two spaces on either side.
+        let inner = (max_length + 4).max(7) | 1;
+
+        let geometry = Geometry::new(inner);
+        let width = geometry.width;
+        let height = 17;
+
+        let mut display = Canvas {
+            chars: vec![' '; (width + 1) * height],
+            paint: vec![Highlight::None; (width + 1) * height],
+        };
+        for r in 0 .. height {
+            display.chars[(width + 1) * r + width] = '\n';
+        }
+
+        let r = self.rooms[room_idx];
+
+        for &(rr, d) in r.iter() {
+            let (row, col) = match d {
+                North => (1, geometry.north_col),
+                South => (11, geometry.north_col),
+                East => (6, geometry.east_col),
+                West => (6, 1),
+            };
+
+            geometry.set_room(&mut display, self.rooms[rr], rr, &annotate(rr), false, row, col);
+        }
+
+        geometry.set_room(&mut display, r, room_idx, &annotate(room_idx), true, 6, geometry.center_col);
+
+        display.render(renderer)
+    }
+}
+
+// The characters to display, along with how each one is highlighted
+struct Canvas {
+    chars: Vec<char>,
+    paint: Vec<Highlight>,
+}
+
+impl Canvas {
+    fn render(&self, renderer: &dyn Renderer) -> String {
+        let mut out = String::new();
+        let mut run = String::new();
+        let mut run_paint = Highlight::None;
+
+        for (&c, &p) in self.chars.iter().zip(&self.paint) {
+            if p != run_paint || c == '\n' {
+                out.push_str(&renderer.paint(&run, run_paint));
+                run.clear();
+                run_paint = p;
+            }
+
+            if c == '\n' {
+                out.push(c);
+            } else {
+                run.push(renderer.wall(c));
+            }
+        }
+
+        out.push_str(&renderer.paint(&run, run_paint));
+        out
+    }
+}
+
+// The sizes and positions of everything in the display, given the inside width of the
+// neighbouring rooms
+struct Geometry {
+    // inside width of the neighbouring rooms; the center room is `inner + 2`
+    inner: usize,
+    // the number of columns in the display, not including the newline
+    width: usize,
+
+    center_col: usize,
+    // also the column for the room to the south
+    north_col: usize,
+    east_col: usize,
+}
+
+impl Geometry {
+    fn new(inner: usize) -> Self {
+        // The outside width of the neighbouring rooms. The room to the west starts in column 1
+        // and the center room is two past its end, with the rooms above and below it one further
+        // in. The room to the east is two past the end of the center room.
+        let outer = inner + 2;
+        let center_col = 1 + outer + 2;
+        let north_col = center_col + 1;
+        let east_col = center_col + (outer + 2) + 2;
+
+        Geometry {
+            inner,
+            width: east_col + outer + 1,
+            center_col,
+            north_col,
+            east_col,
+        }
+    }
+
+    // row counted down from the top, starting at 0
+    fn idx(&self, row: usize, col: usize) -> usize {
+        // add one for newline
+        (self.width + 1) * row + col
+    }
+
+    // new strings should be from top down
+    fn overwrite(&self, data: &mut Canvas, new: Vec<String>, row: usize, col: usize) {
+        self.overwrite_painted(data, new, row, col, Highlight::None)
+    }
+
+    fn overwrite_painted(&self, data: &mut Canvas, new: Vec<String>, row: usize, col: usize, paint: Highlight) {
+        for (r, s) in new.iter().enumerate() {
+            let chars = s.chars().collect::<Vec<_>>();
+            let i = self.idx(row + r, col);
+            data.chars[i .. i + chars.len()].copy_from_slice(&chars);
+            for p in &mut data.paint[i .. i + chars.len()] {
+                *p = paint;
+            }
+        }
+    }
+
+    // `top_bar` includes the corners and (if it's not the center) the
+    // line above
+    //
+    // Vector has length two
+    fn top_bar(&self, r: Room, center: bool) -> Vec<String> {
+        let has_room_above = r.iter().any(|(_,d)| d == &North);
+
+        // the width of the bar between each corner and the tunnel
+        let side = (self.inner - 5) / 2;
+
+        if center {
+            if has_room_above {
+                vec![
+                    format!(" ╚{0}╗   ╔{0}╝ ", "═".repeat(side)),
+                    format!("╔{0}╝   ╚{0}╗", "═".repeat(side + 1)),
+                ]
+            } else {
+                vec![
+                    "".into(),
+                    format!("╔{}╗", "═".repeat(self.inner + 2)),
+                ]
+            }
+        } else {
+            if has_room_above {
+                vec![
+                    format!("{0}.{0}", " ".repeat(self.inner / 2 + 1)),
+                    format!("╔{0}╝ . ╚{0}╗", "═".repeat(side)),
+                ]
+            } else {
+                vec![
+                    " ".repeat(self.inner + 2),
+                    format!("╔{}╗", "═".repeat(self.inner)),
+                ]
+            }
+        }
+    }
+
+    fn bot_bar(&self, r: Room, center: bool) -> Vec<String> {
+        let has_room_below = r.iter().any(|(_,d)| d == &South);
+
+        let side = (self.inner - 5) / 2;
+
+        if center {
+            if has_room_below {
+                vec![
+                    format!("╚{0}╗   ╔{0}╝", "═".repeat(side + 1)),
+                    format!(" ╔{0}╝   ╚{0}╗ ", "═".repeat(side)),
+                ]
+            } else {
+                vec![format!("╚{}╝", "═".repeat(self.inner + 2))]
+            }
+        } else {
+            if has_room_below {
+                vec![
+                    format!("╚{0}╗ . ╔{0}╝", "═".repeat(side)),
+                    format!("{0}.{0}", " ".repeat(self.inner / 2 + 1)),
+                ]
+            } else {
+                vec![
+                    format!("╚{}╝", "═".repeat(self.inner)),
+                    " ".repeat(self.inner + 2),
+                ]
+            }
+        }
+    }
+
+    // doesn't include top/bottom bar
+    // Each string is of width two
+    fn left_side(&self, r: Room, center: bool) -> Vec<String> {
+        let has_room_left = r.iter().any(|(_,d)| d == &West);
+
+        if center {
+            if has_room_left {
+                vec![
+                    "╚══╝".into(),
+                    "    ".into(),
+                    "╔══╗".into(),
+                ]
+            } else {
+                vec![
+                    "   ║".into(),
+                    "   ║".into(),
+                    "   ║".into(),
+                ]
+            }
+        } else {
+            if has_room_left {
+                vec![
+                    " ╝".into(),
+                    "..".into(),
+                    " ╗".into(),
+                ]
+            } else {
+                vec![
+                    " ║".into(),
+                    " ║".into(),
+                    " ║".into(),
+                ]
+            }
+        }
+    }
+
+    fn right_side(&self, r: Room, center: bool) -> Vec<String> {
+        let has_room_right = r.iter().any(|(_,d)| d == &East);
+
+        if center {
+            if has_room_right {
+                vec![
+                    "╚══╝".into(),
+                    "    ".into(),
+                    "╔══╗".into(),
+                ]
+            } else {
+                vec![
+                    "║".into(),
+                    "║".into(),
+                    "║".into(),
+                ]
+            }
+        } else {
+            if has_room_right {
+                vec![
+                    "╚ ".into(),
+                    "..".into(),
+                    "╔ ".into(),
+                ]
+            } else {
+                vec![
+                    "║ ".into(),
+                    "║ ".into(),
+                    "║ ".into(),
+                ]
+            }
+        }
+    }
+
+    // position should be of the top-left corner - so we'll expect to go
+    // up and to the left
+    #[allow(clippy::too_many_arguments)]
+    fn set_room(&self, data: &mut Canvas, r: Room, idx: usize, note: &Annotation, center: bool, row: usize, col: usize) {
+        // the inside width of this room
+        let inner = if center { self.inner + 2 } else { self.inner };
+
+        if center {
+            self.overwrite(data, self.top_bar(r, center), row-1, col);
+            self.overwrite(data, self.left_side(r, center), row+1, col-3);
+        } else {
+            self.overwrite(data, self.top_bar(r, center), row-1, col);
+            self.overwrite(data, self.left_side(r, center), row+1, col-1);
+        }
+        self.overwrite(data, self.right_side(r, center), row+1, col+inner+1);
+
+        self.overwrite(data, self.bot_bar(r, center), row+4, col);
+
+        // do work on the middle section
+        if center {
+            let middle = vec![
+                " ".repeat(inner),
+                format!("{:^1$}", "You", inner),
+                format!("{:^1$}", idx, inner),
+            ];
+            self.overwrite_painted(data, middle, row+1, col+1, note.highlight);
+        } else {
+            let label = note.label.chars().take(inner).collect::<String>();
+            self.overwrite(data, vec![" ".repeat(inner)], row+1, col+1);
+            self.overwrite_painted(data, vec![format!("{:^1$}", idx, inner)], row+2, col+1, note.highlight);
+            self.overwrite_painted(data, vec![format!("{:^1$}", label, inner)], row+3, col+1, note.label_highlight);
+        }
+    }
+}
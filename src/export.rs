@@ -0,0 +1,429 @@
+//! Writing out a whole cave, to look at outside of the game
+//!
+//! Caves can be written as Graphviz DOT, as JSON, or as an SVG picture that's laid out by the
+//! same code as the in-game map, so that nothing else needs to be installed to see one. Every
+//! format labels each end of a tunnel with the direction it leaves its room in, and marks the
+//! starting room and the rooms with hazards in them.
+
+use serde::Serialize;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use crate::layout;
+use crate::maze::{Direction, Maze};
+
+// The distance between the centers of neighbouring rooms in an SVG, and the radius of each room
+const SVG_SPACING: f32 = 90.0;
+const SVG_RADIUS: f32 = 20.0;
+// How far along a tunnel its direction labels are drawn, from each end
+const SVG_LABEL_AT: f32 = 0.3;
+
+/// The formats a cave can be exported in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Dot,
+    Json,
+    Svg,
+}
+
+// The layout of the JSON export. Directions are written the same way as everywhere else in the
+// game, rather than as they're stored in saved games.
+#[derive(Serialize)]
+struct JsonCave<'a> {
+    start: usize,
+    bats: &'a [usize],
+    pits: &'a [usize],
+    wumpuses: &'a [usize],
+    rooms: Vec<JsonRoom>,
+}
+
+#[derive(Serialize)]
+struct JsonRoom {
+    room: usize,
+    markers: Vec<&'static str>,
+    tunnels: Vec<JsonTunnel>,
+}
+
+#[derive(Serialize)]
+struct JsonTunnel {
+    direction: String,
+    to: usize,
+}
+
+// A single tunnel, seen from both ends: the two rooms, and the direction it leaves each of them
+type Tunnel = (usize, Direction, usize, Direction);
+
+impl Maze {
+    /// Writes out the whole cave
+    pub fn export(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Dot => self.to_dot(),
+            ExportFormat::Json => self.to_json(),
+            ExportFormat::Svg => self.to_svg(),
+        }
+    }
+
+    // Every tunnel in the cave, once each
+    //
+    // Two rooms can be joined by more than one tunnel, so the n-th tunnel from `a` to `b` is
+    // paired up with the n-th tunnel back from `b` to `a`.
+    fn tunnels(&self) -> Vec<Tunnel> {
+        let mut tunnels = Vec::new();
+        for (a, room) in self.rooms.iter().enumerate() {
+            for (i, &(b, dir)) in room.iter().enumerate() {
+                if b < a {
+                    continue;
+                }
+
+                let nth = room[..i].iter().filter(|(r, _)| *r == b).count();
+                let back = self.rooms.get(b).and_then(|other| {
+                    other.iter().filter(|(r, _)| *r == a).nth(nth)
+                });
+
+                // A valid cave always has a way back, but a broken one is still shown as best
+                // it can be
+                let back_dir = back.map_or(dir.opposite(), |&(_, d)| d);
+                tunnels.push((a, dir, b, back_dir));
+            }
+        }
+        tunnels
+    }
+
+    // The markers for a room: whether it's the start, and which hazards are in it
+    fn markers(&self, room: usize) -> Vec<&'static str> {
+        let mut markers = Vec::new();
+        if room == self.start {
+            markers.push("start");
+        }
+        if self.bats.contains(&room) {
+            markers.push("bats");
+        }
+        if self.pits.contains(&room) {
+            markers.push("pit");
+        }
+        if self.wumpuses.contains(&room) {
+            markers.push("wumpus");
+        }
+        markers
+    }
+
+    fn to_dot(&self) -> String {
+        let mut out = String::new();
+        out.push_str("graph cave {\n");
+        out.push_str("    node [shape=circle, style=filled, fillcolor=white];\n");
+
+        for room in 0..self.rooms.len() {
+            let markers = self.markers(room);
+            if markers.is_empty() {
+                writeln!(out, "    {};", room).unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "    {} [label=\"{}\\n{}\", fillcolor=\"{}\"];",
+                    room,
+                    room,
+                    markers.join(", "),
+                    fill(&markers)
+                )
+                .unwrap();
+            }
+        }
+
+        for (a, dir_a, b, dir_b) in self.tunnels() {
+            writeln!(
+                out,
+                "    {} -- {} [taillabel=\"{}\", headlabel=\"{}\"];",
+                a, b, dir_a, dir_b
+            )
+            .unwrap();
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    fn to_json(&self) -> String {
+        let rooms = self
+            .rooms
+            .iter()
+            .enumerate()
+            .map(|(i, room)| JsonRoom {
+                room: i,
+                markers: self.markers(i),
+                tunnels: room
+                    .iter()
+                    .map(|&(to, dir)| JsonTunnel {
+                        direction: dir.to_string(),
+                        to,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let cave = JsonCave {
+            start: self.start,
+            bats: &self.bats,
+            pits: &self.pits,
+            wumpuses: &self.wumpuses,
+            rooms,
+        };
+        serde_json::to_string_pretty(&cave).unwrap() + "\n"
+    }
+
+    fn to_svg(&self) -> String {
+        let tunnels = self.tunnels();
+        let edges: Vec<_> = tunnels.iter().map(|&(a, _, b, _)| (a, b)).collect();
+        let coords = layout::layered(self.rooms.len(), &edges, self.start);
+
+        let min_x = coords.iter().map(|c| c.0).fold(0.0, f32::min);
+        let max_x = coords.iter().map(|c| c.0).fold(0.0, f32::max);
+        let max_y = coords.iter().map(|c| c.1).fold(0.0, f32::max);
+
+        let margin = SVG_SPACING / 2.0;
+        let pos = |room: usize| {
+            let (x, y) = coords[room];
+            (margin + (x - min_x) * SVG_SPACING, margin + y * SVG_SPACING)
+        };
+        let width = 2.0 * margin + (max_x - min_x) * SVG_SPACING;
+        // The legend goes along the bottom
+        let height = 2.0 * margin + max_y * SVG_SPACING + 30.0;
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" text-anchor=\"middle\">",
+            w = width.max(360.0),
+            h = height,
+        )
+        .unwrap();
+        out.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+
+        for &(a, dir_a, b, dir_b) in &tunnels {
+            let ((x1, y1), (x2, y2)) = (pos(a), pos(b));
+            writeln!(
+                out,
+                "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#888\"/>",
+                x1, y1, x2, y2
+            )
+            .unwrap();
+
+            for &(t, dir) in &[(SVG_LABEL_AT, dir_a), (1.0 - SVG_LABEL_AT, dir_b)] {
+                writeln!(
+                    out,
+                    "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\" fill=\"#555\">{}</text>",
+                    x1 + (x2 - x1) * t,
+                    y1 + (y2 - y1) * t + 3.0,
+                    short(dir)
+                )
+                .unwrap();
+            }
+        }
+
+        for room in 0..self.rooms.len() {
+            let (x, y) = pos(room);
+            let markers = self.markers(room);
+            writeln!(
+                out,
+                "<g><title>{}</title><circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"{}\" \
+                 stroke=\"black\"/><text x=\"{:.1}\" y=\"{:.1}\" font-size=\"12\" fill=\"{}\">{}</text></g>",
+                title(room, &markers),
+                x,
+                y,
+                SVG_RADIUS,
+                fill(&markers),
+                x,
+                y + 4.0,
+                if markers.contains(&"pit") { "white" } else { "black" },
+                room
+            )
+            .unwrap();
+        }
+
+        let mut x = 10.0;
+        let y = height - 15.0;
+        for &marker in &["start", "bats", "pit", "wumpus"] {
+            writeln!(
+                out,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"6\" fill=\"{}\" stroke=\"black\"/>\
+                 <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"12\" text-anchor=\"start\">{}</text>",
+                x + 6.0,
+                y,
+                fill(&[marker]),
+                x + 16.0,
+                y + 4.0,
+                marker
+            )
+            .unwrap();
+            x += 80.0;
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+// The colour to fill a room with. Hazards take priority over the start, and the wumpus over
+// everything else.
+fn fill(markers: &[&str]) -> &'static str {
+    if markers.contains(&"wumpus") {
+        "#e06060"
+    } else if markers.contains(&"pit") {
+        "#404040"
+    } else if markers.contains(&"bats") {
+        "#b080e0"
+    } else if markers.contains(&"start") {
+        "#80d080"
+    } else {
+        "white"
+    }
+}
+
+// The tooltip for a room in an SVG
+fn title(room: usize, markers: &[&str]) -> String {
+    if markers.is_empty() {
+        format!("room {}", room)
+    } else {
+        format!("room {}: {}", room, markers.join(", "))
+    }
+}
+
+fn short(dir: Direction) -> &'static str {
+    match dir {
+        Direction::North => "N",
+        Direction::South => "S",
+        Direction::East => "E",
+        Direction::West => "W",
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            ExportFormat::Dot => "dot",
+            ExportFormat::Json => "json",
+            ExportFormat::Svg => "svg",
+        };
+
+        f.write_str(s)
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "dot" => Ok(ExportFormat::Dot),
+            "json" => Ok(ExportFormat::Json),
+            "svg" => Ok(ExportFormat::Svg),
+            _ => Err(format!("unknown format '{}', expected dot, json or svg", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The cave from the level docs, with the player starting in room 1 and one of each hazard
+    const QUAD: &str = r#"
+        start = 1
+        bats = [2]
+        pits = [3]
+        wumpuses = [0]
+
+        [rooms]
+        0 = [[1, "west"], [2, "north"], [3, "east"]]
+        1 = [[0, "east"], [2, "west"], [3, "north"]]
+        2 = [[0, "south"], [1, "east"], [3, "west"]]
+        3 = [[0, "west"], [1, "south"], [2, "east"]]
+    "#;
+
+    // Each tunnel in the cave, with the direction it leaves each end in
+    const TUNNELS: [Tunnel; 6] = [
+        (0, Direction::West, 1, Direction::East),
+        (0, Direction::North, 2, Direction::South),
+        (0, Direction::East, 3, Direction::West),
+        (1, Direction::West, 2, Direction::East),
+        (1, Direction::North, 3, Direction::South),
+        (2, Direction::West, 3, Direction::East),
+    ];
+
+    fn quad() -> Maze {
+        Maze::from_level(QUAD, 2).unwrap()
+    }
+
+    #[test]
+    fn dot_has_every_tunnel_once() {
+        let dot = quad().export(ExportFormat::Dot);
+
+        assert_eq!(dot.matches(" -- ").count(), TUNNELS.len());
+        for &(a, dir_a, b, dir_b) in &TUNNELS {
+            let line =
+                format!("{} -- {} [taillabel=\"{}\", headlabel=\"{}\"];", a, b, dir_a, dir_b);
+            assert_eq!(dot.matches(&line).count(), 1, "{}", line);
+        }
+
+        assert!(dot.contains("0 [label=\"0\\nwumpus\""));
+        assert!(dot.contains("1 [label=\"1\\nstart\""));
+        assert!(dot.contains("2 [label=\"2\\nbats\""));
+        assert!(dot.contains("3 [label=\"3\\npit\""));
+    }
+
+    #[test]
+    fn json_has_every_tunnel_and_marker() {
+        let json: serde_json::Value =
+            serde_json::from_str(&quad().export(ExportFormat::Json)).unwrap();
+
+        assert_eq!(json["start"], 1);
+        assert_eq!(json["bats"], serde_json::json!([2]));
+        assert_eq!(json["pits"], serde_json::json!([3]));
+        assert_eq!(json["wumpuses"], serde_json::json!([0]));
+
+        let rooms = json["rooms"].as_array().unwrap();
+        let markers: Vec<_> = rooms.iter().map(|r| r["markers"].clone()).collect();
+        assert_eq!(
+            markers,
+            [
+                serde_json::json!(["wumpus"]),
+                serde_json::json!(["start"]),
+                serde_json::json!(["bats"]),
+                serde_json::json!(["pit"]),
+            ]
+        );
+
+        // Tunnels are listed from both ends, so each one shows up once in each of its rooms
+        let count = |room: usize, dir: Direction, to: usize| {
+            let tunnel = serde_json::json!({ "direction": dir.to_string(), "to": to });
+            rooms[room]["tunnels"].as_array().unwrap().iter().filter(|t| **t == tunnel).count()
+        };
+        for &(a, dir_a, b, dir_b) in &TUNNELS {
+            assert_eq!(count(a, dir_a, b), 1);
+            assert_eq!(count(b, dir_b, a), 1);
+        }
+        assert!(rooms.iter().all(|r| r["tunnels"].as_array().unwrap().len() == 3));
+    }
+
+    #[test]
+    fn svg_has_every_tunnel_once() {
+        let svg = quad().export(ExportFormat::Svg);
+
+        // Each tunnel is a line with a label at each end
+        assert_eq!(svg.matches("<line ").count(), TUNNELS.len());
+        let labels: Vec<_> = svg
+            .lines()
+            .filter(|line| line.starts_with("<text ") && line.contains("font-size=\"10\""))
+            .map(|line| line.split('>').nth(1).unwrap().trim_end_matches("</text"))
+            .collect();
+        assert_eq!(labels.len(), 2 * TUNNELS.len());
+        for (&(_, dir_a, _, dir_b), pair) in TUNNELS.iter().zip(labels.chunks(2)) {
+            assert_eq!(pair, [short(dir_a), short(dir_b)]);
+        }
+
+        assert!(svg.contains("<title>room 0: wumpus</title>"));
+        assert!(svg.contains("<title>room 1: start</title>"));
+        assert!(svg.contains("<title>room 2: bats</title>"));
+        assert!(svg.contains("<title>room 3: pit</title>"));
+    }
+}
@@ -0,0 +1,906 @@
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::behaviour::{Behaviour, Cave};
+use crate::deduce::Knowledge;
+use crate::maze::{Direction, Maze, Room};
+use crate::notes::Marker;
+use crate::rng::GameRng;
+use crate::rules::Rules;
+
+/// A single thing the player can do on their turn
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    /// Walk through the tunnel in the given direction
+    Move(Direction),
+
+    /// Fire an arrow along the given path, one direction per room it passes through. The length
+    /// of the path is the distance of the shot; how many arrows that costs depends on the
+    /// `Rules`.
+    Shoot(Vec<Direction>),
+
+    /// Fire an arrow through the given rooms, declared all at once. Wherever the next room isn't
+    /// next to the one the arrow is in, it goes down a random tunnel instead. The costs are the
+    /// same as for `Shoot`.
+    ShootRooms(Vec<usize>),
+}
+
+/// Everything that the engine can report back to the player
+///
+/// The `Display` implementation gives the message that the terminal front-end prints.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event {
+    /// There's a wumpus in a neighbouring room
+    SmellWumpus,
+    /// There are bats in a neighbouring room
+    HearBats,
+    /// There's a pit in a neighbouring room
+    FeelWind,
+
+    /// The player walked into the bats' room and was dropped somewhere else
+    BatsCarriedYou { to: usize },
+    FellInPit,
+    EatenByWumpus,
+
+    /// The last wumpus in the cave was killed
+    WumpusKilled,
+    /// A wumpus was killed, but there are still more left
+    KilledAWumpus { remaining: usize },
+    ShotYourself,
+    /// Both the wumpus and the player were in the room the arrow entered
+    ShotYourselfAndWumpus,
+    ArrowMissed,
+    OutOfArrows,
+    /// The player picked up arrows that had missed earlier
+    FoundArrows { count: i32 },
+}
+
+/// The reasons that an `Action` might be rejected. None of these use up the player's turn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ActionError {
+    /// There's no tunnel in the given direction. `from` is the room the player (or the arrow)
+    /// was in at the time.
+    NoTunnel { from: usize, direction: Direction },
+    /// Shots must travel between `min` and `max` rooms, as set by the rules
+    BadShotDistance { min: usize, max: usize },
+    NotEnoughArrows { have: i32 },
+    /// The room doesn't exist
+    NoSuchRoom { room: usize },
+    /// A path given by room numbers can't double back to the room it was in two rooms before
+    TooCrooked,
+    GameOver,
+}
+
+/// The game engine. This holds the cave and the player's position, and doesn't do any I/O.
+///
+/// Every random decision made during the game comes from the single `GameRng` it was given.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Game {
+    maze: Maze,
+    rules: Rules,
+    player: usize,
+    // Every room the player has been in, including the current one
+    #[serde(default)]
+    visited: BTreeSet<usize>,
+    // What the player could have worked out from the warnings they've been given
+    #[serde(default)]
+    knowledge: Knowledge,
+    // The player's own markers on rooms, and whether to fill in the rest automatically
+    #[serde(default)]
+    pub(crate) marks: BTreeMap<usize, Vec<Marker>>,
+    #[serde(default)]
+    pub(crate) auto_mark: bool,
+    // Replaces the wumpus behaviour from the rules. This isn't saved, so a loaded game always
+    // goes back to the rules.
+    #[serde(skip)]
+    behaviour: Option<Arc<dyn Behaviour>>,
+    rng: GameRng,
+    // The seed the game was generated from, if it was
+    #[serde(default)]
+    seed: Option<u64>,
+    // The number of actions taken so far
+    turn: u32,
+    over: bool,
+    // The event that ended the game, once it's over
+    #[serde(default)]
+    ending: Option<Event>,
+    // The number of shots taken, and of times that bats carried the player off
+    #[serde(default)]
+    shots: u32,
+    #[serde(default)]
+    carries: u32,
+    // Whether any turns have been taken back, in practice mode
+    #[serde(default)]
+    rewound: bool,
+}
+
+impl Event {
+    /// Returns whether this event ends the game
+    pub fn is_fatal(&self) -> bool {
+        use Event::*;
+
+        match self {
+            SmellWumpus | HearBats | FeelWind | BatsCarriedYou { .. } | KilledAWumpus { .. }
+            | ArrowMissed | FoundArrows { .. } => false,
+            FellInPit | EatenByWumpus | WumpusKilled | ShotYourself | ShotYourselfAndWumpus
+            | OutOfArrows => true,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Move(d) => write!(f, "move {}", d),
+            Action::Shoot(path) => {
+                write!(f, "shoot")?;
+                for d in path {
+                    write!(f, " {}", d)?;
+                }
+                Ok(())
+            }
+            Action::ShootRooms(rooms) => {
+                write!(f, "shoot")?;
+                for r in rooms {
+                    write!(f, " {}", r)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Event::*;
+
+        match self {
+            SmellWumpus => write!(f, "You smell something terrible nearby."),
+            HearBats => write!(f, "You hear a rustling."),
+            FeelWind => write!(f, "You feel a cold wind blowing from a nearby cavern."),
+            BatsCarriedYou { .. } => write!(f, "The bats whisk you away!"),
+            FellInPit => write!(f, "You fall into a bottomless pit!"),
+            EatenByWumpus => write!(f, "You woke up the wumpus and he ate you!"),
+            WumpusKilled => write!(f, "You killed the Wumpus!"),
+            KilledAWumpus { remaining: 1 } => {
+                write!(f, "You killed a Wumpus! There's still one more out there.")
+            }
+            KilledAWumpus { remaining } => {
+                write!(f, "You killed a Wumpus! There are still {} more out there.", remaining)
+            }
+            ShotYourself => write!(f, "You killed... yourself."),
+            ShotYourselfAndWumpus => write!(
+                f,
+                "You have done the impossible!\n\
+                 You've killed yourself and the Wumpus in one fell swoop!"
+            ),
+            ArrowMissed => write!(f, "You didn't hit anything..."),
+            OutOfArrows => write!(f, "You ran out of arrows! You lose."),
+            FoundArrows { count: 1 } => write!(f, "You found an arrow on the floor."),
+            FoundArrows { count } => write!(f, "You found {} arrows on the floor.", count),
+        }
+    }
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ActionError::*;
+
+        match self {
+            NoTunnel { .. } => write!(f, "There's no tunnel that way!"),
+            BadShotDistance { min, max } => {
+                write!(f, "Please enter a number between {} and {}.", min, max)
+            }
+            NotEnoughArrows { have } => write!(
+                f,
+                "You don't have enough arrows to shoot that far!\nYou only have {}.",
+                have
+            ),
+            NoSuchRoom { room } => write!(f, "There's no room {}!", room),
+            TooCrooked => write!(f, "Arrows aren't that crooked - try another room."),
+            GameOver => write!(f, "The game is already over."),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
+impl Game {
+    /// Starts a new game in the given maze, with the player in its starting room
+    pub fn new(maze: Maze, rng: GameRng) -> Self {
+        Self::with_rules(maze, Rules::default(), rng)
+    }
+
+    /// Like `new`, but with something other than the default rules
+    pub fn with_rules(maze: Maze, rules: Rules, rng: GameRng) -> Self {
+        let start = maze.start;
+        let mut game = Self {
+            maze,
+            rules,
+            player: start,
+            visited: std::iter::once(start).collect(),
+            knowledge: Knowledge::default(),
+            marks: BTreeMap::new(),
+            auto_mark: false,
+            behaviour: None,
+            rng,
+            seed: None,
+            turn: 0,
+            over: false,
+            ending: None,
+            shots: 0,
+            carries: 0,
+            rewound: false,
+        };
+
+        let senses = game.senses();
+        game.knowledge.observe(&game.maze.rooms, start, &senses);
+        game
+    }
+
+    /// Generates a new maze with `4 + 2 * adds` rooms and starts a game in it, with hazards as
+    /// given by the rules. Both the maze and the rest of the game are determined entirely by the
+    /// seed.
+    ///
+    /// If the rules ask for the classic cave, that's used instead and `adds` is ignored.
+    pub fn from_seed(adds: u32, rules: Rules, seed: u64) -> Self {
+        let mut rng = GameRng::from_seed(seed);
+        let maze = if rules.classic_cave {
+            Maze::classic(&rules, &mut rng)
+        } else {
+            Maze::generate(adds, &rules, &mut rng)
+        };
+
+        let mut game = Self::with_rules(maze, rules, rng);
+        game.seed = Some(seed);
+        game
+    }
+
+    /// Starts a game in the cave from the original 1973 game, following its rules
+    pub fn classic(seed: u64) -> Self {
+        Self::from_seed(0, Rules::classic(), seed)
+    }
+
+    /// Makes the wumpuses follow the given behaviour instead of the profile in the rules. Saved
+    /// games only store the rules, so this needs to be set again after loading.
+    pub fn set_behaviour(&mut self, behaviour: Arc<dyn Behaviour>) {
+        self.behaviour = Some(behaviour);
+    }
+
+    pub fn maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// The room that the player is currently in
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    /// Everything that can be deduced from the warnings the player has been given so far
+    pub fn knowledge(&self) -> &Knowledge {
+        &self.knowledge
+    }
+
+    /// Every room that the player has been in so far, including the one they're in now
+    pub fn visited(&self) -> &BTreeSet<usize> {
+        &self.visited
+    }
+
+    /// The seed that the game was generated from, which `from_seed` can use to play it again.
+    /// Games in hand-made caves don't have one.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// The number of actions that have been taken so far
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    /// The event that ended the game, if it's over. Games saved before this was kept won't have
+    /// one.
+    pub fn ending(&self) -> Option<Event> {
+        self.ending
+    }
+
+    /// The number of arrows that have been shot, counting each shot once however far it went
+    pub fn shots(&self) -> u32 {
+        self.shots
+    }
+
+    /// The number of times that bats have carried the player off
+    pub fn bat_carries(&self) -> u32 {
+        self.carries
+    }
+
+    /// Returns whether any turns have been taken back in this game
+    pub fn rewound(&self) -> bool {
+        self.rewound
+    }
+
+    /// Records that turns have been taken back, by going back to an earlier copy of the game.
+    /// Rewound games don't go in the high score table.
+    pub fn set_rewound(&mut self) {
+        self.rewound = true;
+    }
+
+    /// Returns whether the game is over because every wumpus was killed
+    pub fn won(&self) -> bool {
+        // Shooting the last wumpus and the player at once doesn't remove the wumpus
+        self.over && self.maze.wumpuses.is_empty()
+    }
+
+    /// Gives what the player can sense from their current room
+    pub fn senses(&self) -> Vec<Event> {
+        let near = |target: usize| self.maze.rooms[self.player].iter().any(|(r, _)| *r == target);
+
+        let mut events = Vec::new();
+        if self.maze.wumpuses.iter().any(|&w| near(w)) {
+            events.push(Event::SmellWumpus);
+        }
+        if self.maze.bats.iter().any(|&b| near(b)) {
+            events.push(Event::HearBats);
+        }
+        if self.maze.pits.iter().any(|&p| near(p)) {
+            events.push(Event::FeelWind);
+        }
+
+        events
+    }
+
+    /// Performs a single action, returning everything that happened as a result
+    ///
+    /// If the player survives, the returned events end with what they can sense from the room
+    /// they finish in.
+    pub fn act(&mut self, action: Action) -> Result<Vec<Event>, ActionError> {
+        if self.over {
+            return Err(ActionError::GameOver);
+        }
+
+        let mut events = match &action {
+            Action::Move(d) => self.do_move(*d)?,
+            Action::Shoot(path) => self.do_shoot(path)?,
+            Action::ShootRooms(rooms) => self.do_shoot_rooms(rooms)?,
+        };
+
+        if !events.iter().any(Event::is_fatal) {
+            self.end_turn(&mut events);
+        }
+
+        self.turn += 1;
+        if let Action::Shoot(_) | Action::ShootRooms(_) = action {
+            self.shots += 1;
+        }
+        let carried = events.iter().filter(|e| matches!(e, Event::BatsCarriedYou { .. }));
+        self.carries += carried.count() as u32;
+
+        self.ending = events.iter().copied().find(Event::is_fatal);
+        self.over = self.ending.is_some();
+        if !self.over {
+            // Any shot that doesn't end the game either misses, waking the wumpuses, or kills
+            // one of them. Either way, we can't tell where the rest are anymore.
+            if let Action::Shoot(_) | Action::ShootRooms(_) = action {
+                self.knowledge.wumpus_moved();
+            }
+
+            let senses = self.senses();
+            self.knowledge.observe(&self.maze.rooms, self.player, &senses);
+            events.extend(senses);
+        }
+
+        Ok(events)
+    }
+
+    /// Returns the rooms that an arrow shot by `action` would fly through, if it was taken now,
+    /// without shooting it. Gives `None` for anything that isn't a shot, or a shot down a tunnel
+    /// that doesn't exist.
+    ///
+    /// Any random tunnels are picked the same way `act` would pick them.
+    pub fn flight(&self, action: &Action) -> Option<Vec<usize>> {
+        match action {
+            Action::Move(_) => None,
+            Action::Shoot(path) => {
+                let mut rooms = Vec::with_capacity(path.len());
+                let mut idx = self.player;
+                for &d in path {
+                    idx = self.follow(idx, d).ok()?;
+                    rooms.push(idx);
+                }
+                Some(rooms)
+            }
+            Action::ShootRooms(targets) => {
+                let mut rng = self.rng;
+                Some(fly(&self.maze.rooms, self.player, targets, &mut rng))
+            }
+        }
+    }
+
+    // Returns the room through the tunnel in the given direction
+    fn follow(&self, from: usize, direction: Direction) -> Result<usize, ActionError> {
+        self.maze.rooms[from]
+            .iter()
+            .find(|(_, d)| *d == direction)
+            .map(|(r, _)| *r)
+            .ok_or(ActionError::NoTunnel { from, direction })
+    }
+
+    fn do_move(&mut self, direction: Direction) -> Result<Vec<Event>, ActionError> {
+        self.player = self.follow(self.player, direction)?;
+
+        let mut events = Vec::new();
+        self.enter_room(&mut events);
+        Ok(events)
+    }
+
+    // Resolves all of the hazards in the player's current room, stopping early if one of them
+    // was fatal
+    fn enter_room(&mut self, events: &mut Vec<Event>) {
+        loop {
+            self.visited.insert(self.player);
+
+            for w in 0..self.maze.wumpuses.len() {
+                if self.maze.wumpuses[w] != self.player {
+                    continue;
+                }
+
+                match self.with_wumpus(w, |b, cave, rng| b.woken(cave, rng)) {
+                    Some(room) => {
+                        self.maze.wumpuses[w] = room;
+                        self.knowledge.wumpus_moved();
+                    }
+                    None => {
+                        events.push(Event::EatenByWumpus);
+                        return;
+                    }
+                }
+            }
+
+            if self.maze.bats.contains(&self.player) {
+                // pick a new room to go into, and then deal with whatever's there
+                self.knowledge.found_bats(self.player);
+                self.player = self.bats_drop();
+                events.push(Event::BatsCarriedYou { to: self.player });
+                continue;
+            }
+
+            if self.maze.pits.contains(&self.player) {
+                events.push(Event::FellInPit);
+                return;
+            }
+
+            if let Some(count) = self.maze.dropped.remove(&self.player) {
+                self.maze.arrows += count;
+                events.push(Event::FoundArrows { count });
+            }
+
+            return;
+        }
+    }
+
+    // Picks the room that bats carry the player to
+    fn bats_drop(&mut self) -> usize {
+        let n_rooms = self.maze.rooms.len();
+        if self.rules.bats_into_hazards {
+            return (self.rng.gen::<f32>() * n_rooms as f32) as usize;
+        }
+
+        let maze = &self.maze;
+        let empty: Vec<usize> = (0..n_rooms)
+            .filter(|r| !(maze.bats.contains(r) || maze.pits.contains(r) || maze.wumpuses.contains(r)))
+            .collect();
+
+        // There's always somewhere empty while the hazards fit in the cave, but a hand-made one
+        // might not have anywhere
+        if empty.is_empty() {
+            (self.rng.gen::<f32>() * n_rooms as f32) as usize
+        } else {
+            empty[(self.rng.gen::<f32>() * empty.len() as f32) as usize]
+        }
+    }
+
+    // Asks the wumpus with the given index what it does, using the game's behaviour
+    fn with_wumpus<T, F>(&mut self, w: usize, f: F) -> T
+    where
+        F: FnOnce(&dyn Behaviour, Cave, &mut GameRng) -> T,
+    {
+        let behaviour: &dyn Behaviour = match &self.behaviour {
+            Some(b) => b.as_ref(),
+            None => &self.rules.wumpus,
+        };
+
+        let cave = Cave {
+            rules: &self.rules,
+            rooms: &self.maze.rooms,
+            wumpus: self.maze.wumpuses[w],
+            player: self.player,
+            // Actions are only counted once they're done
+            turn: self.turn + 1,
+        };
+
+        f(behaviour, cave, &mut self.rng)
+    }
+
+    // Gives every wumpus the chance to move once the player's action is done
+    fn end_turn(&mut self, events: &mut Vec<Event>) {
+        let mut moved = false;
+        for w in 0..self.maze.wumpuses.len() {
+            let room = self.with_wumpus(w, |b, cave, rng| b.turn_ended(cave, rng));
+            moved |= room != self.maze.wumpuses[w];
+            self.maze.wumpuses[w] = room;
+        }
+
+        if moved {
+            self.knowledge.wumpus_moved();
+        }
+        if self.maze.wumpuses.contains(&self.player) {
+            events.push(Event::EatenByWumpus);
+        }
+    }
+
+    // Checks that a shot of the given distance can be made, returning how many arrows it costs
+    fn shot_cost(&self, dist: usize) -> Result<i32, ActionError> {
+        let cost = self.rules.shot_cost(dist as i32);
+        let (min, max) = (self.rules.min_shot, self.rules.max_shot);
+        if dist < min || dist > max {
+            Err(ActionError::BadShotDistance { min, max })
+        } else if cost > self.maze.arrows {
+            Err(ActionError::NotEnoughArrows {
+                have: self.maze.arrows,
+            })
+        } else {
+            Ok(cost)
+        }
+    }
+
+    fn do_shoot(&mut self, path: &[Direction]) -> Result<Vec<Event>, ActionError> {
+        let cost = self.shot_cost(path.len())?;
+
+        // Check the whole path before anything happens, so that a bad path doesn't cost arrows
+        let mut rooms = Vec::with_capacity(path.len());
+        let mut idx = self.player;
+        for &d in path {
+            idx = self.follow(idx, d)?;
+            rooms.push(idx);
+        }
+
+        self.maze.arrows -= cost;
+        Ok(self.fire(rooms, cost))
+    }
+
+    fn do_shoot_rooms(&mut self, targets: &[usize]) -> Result<Vec<Event>, ActionError> {
+        let cost = self.shot_cost(targets.len())?;
+
+        if let Some(&room) = targets.iter().find(|&&r| r >= self.maze.rooms.len()) {
+            return Err(ActionError::NoSuchRoom { room });
+        } else if targets.windows(3).any(|w| w[0] == w[2]) {
+            return Err(ActionError::TooCrooked);
+        }
+
+        self.maze.arrows -= cost;
+        let rooms = fly(&self.maze.rooms, self.player, targets, &mut self.rng);
+        Ok(self.fire(rooms, cost))
+    }
+
+    // Sends an arrow (that's already been paid for) through the rooms. `cost` is the number of
+    // arrows that were used up, which are left where the shot ends if it misses and the rules
+    // allow them to be recovered.
+    fn fire(&mut self, rooms: Vec<usize>, cost: i32) -> Vec<Event> {
+        for &idx in &rooms {
+            let hit = self.maze.wumpuses.iter().position(|&w| w == idx);
+
+            if idx == self.player && hit.is_some() {
+                return vec![Event::ShotYourselfAndWumpus];
+            } else if let Some(w) = hit {
+                self.maze.wumpuses.remove(w);
+                return vec![match self.maze.wumpuses.len() {
+                    0 => Event::WumpusKilled,
+                    remaining => Event::KilledAWumpus { remaining },
+                }];
+            } else if idx == self.player {
+                return vec![Event::ShotYourself];
+            }
+        }
+
+        let mut events = vec![Event::ArrowMissed];
+
+        if self.rules.recover_arrows {
+            let last = *rooms.last().expect("shots go through at least one room");
+            *self.maze.dropped.entry(last).or_default() += cost;
+        }
+
+        // As long as there are enough arrows for another shot, or some to pick up, the game can
+        // go on
+        let cost = self.rules.shot_cost(self.rules.min_shot as i32);
+        if cost > self.maze.arrows && self.maze.dropped.is_empty() {
+            events.push(Event::OutOfArrows);
+            return events;
+        }
+
+        // The noise wakes up every wumpus, each of which might move
+        for w in 0..self.maze.wumpuses.len() {
+            let room = self.with_wumpus(w, |b, cave, rng| b.heard_miss(cave, &rooms, rng));
+            self.maze.wumpuses[w] = room;
+        }
+
+        if self.maze.wumpuses.contains(&self.player) {
+            events.push(Event::EatenByWumpus);
+        }
+
+        events
+    }
+}
+
+// The rooms an arrow flies through when it's aimed at `targets` from `from`. Whenever the next
+// target isn't through any of the tunnels, the arrow goes down a random one instead.
+fn fly(rooms: &[Room], from: usize, targets: &[usize], rng: &mut GameRng) -> Vec<usize> {
+    let mut path = Vec::with_capacity(targets.len());
+    let mut idx = from;
+    for &target in targets {
+        let tunnels = rooms[idx];
+        idx = if tunnels.iter().any(|&(r, _)| r == target) {
+            target
+        } else {
+            tunnels[(rng.gen::<f32>() * 3.0) as usize].0
+        };
+        path.push(idx);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deduce::{Hazard, Status};
+    use crate::maze::Direction::{East, North, South, West};
+    use crate::maze::Hazards;
+
+    // A generated cave with nothing in it, so that each test can put hazards where it wants them
+    fn empty_game(seed: u64) -> Game {
+        let rules = Rules {
+            hazards: Hazards {
+                bats: 0,
+                pits: 0,
+                wumpuses: 0,
+            },
+            ..Rules::default()
+        };
+        let mut rng = GameRng::from_seed(seed);
+        let maze = Maze::generate(8, &rules, &mut rng);
+        Game::with_rules(maze, rules, rng)
+    }
+
+    // The tunnel from one room to a neighbouring one
+    fn towards(game: &Game, from: usize, to: usize) -> Direction {
+        let tunnels = game.maze().rooms[from];
+        tunnels.iter().find(|(r, _)| *r == to).expect("the rooms are next to each other").1
+    }
+
+    // Two rooms in a line from the start, which an arrow can be shot straight through
+    fn two_rooms_out(game: &Game) -> (usize, usize) {
+        let start = game.player();
+        let first = game.maze().rooms[start][0].0;
+        let second = game.maze().rooms[first].iter().find(|(r, _)| *r != start).unwrap().0;
+        (first, second)
+    }
+
+    // Walks through each room's tunnels in turn, until the game is over
+    fn wander(game: &mut Game, turns: usize) -> Vec<Vec<Event>> {
+        (0..turns)
+            .map_while(|i| {
+                let direction = game.maze().rooms[game.player()][i % 3].1;
+                game.act(Action::Move(direction)).ok()
+            })
+            .collect()
+    }
+
+    // Always runs to the same room when it's woken
+    #[derive(Debug)]
+    struct RunTo(usize);
+
+    impl Behaviour for RunTo {
+        fn woken(&self, _: Cave, _: &mut GameRng) -> Option<usize> {
+            Some(self.0)
+        }
+
+        fn heard_miss(&self, cave: Cave, _: &[usize], _: &mut GameRng) -> usize {
+            cave.wumpus
+        }
+    }
+
+    #[test]
+    fn act_ends_with_senses() {
+        let mut game = empty_game(1);
+        let start = game.player();
+        let (next, direction) = game.maze().rooms[start][0];
+        let beyond = game.maze().rooms[next].iter().find(|(r, _)| *r != start).unwrap().0;
+        game.maze.pits = vec![beyond];
+
+        assert_eq!(game.act(Action::Move(direction)), Ok(vec![Event::FeelWind]));
+        assert_eq!(game.player(), next);
+        assert_eq!(game.turn(), 1);
+        assert!(!game.is_over());
+        assert!(game.visited().contains(&next));
+    }
+
+    #[test]
+    fn fatal_event_ends_the_game() {
+        let mut game = empty_game(1);
+        let (next, direction) = game.maze().rooms[game.player()][0];
+        game.maze.pits = vec![next];
+
+        assert_eq!(game.act(Action::Move(direction)), Ok(vec![Event::FellInPit]));
+        assert!(game.is_over());
+        assert_eq!(game.ending(), Some(Event::FellInPit));
+        assert_eq!(game.act(Action::Move(direction)), Err(ActionError::GameOver));
+        assert_eq!(game.turn(), 1);
+    }
+
+    #[test]
+    fn rejected_action_doesnt_use_a_turn() {
+        let mut game = empty_game(1);
+        let from = game.player();
+        let tunnels = game.maze().rooms[from];
+        let direction = [North, South, East, West]
+            .iter()
+            .copied()
+            .find(|d| tunnels.iter().all(|(_, t)| t != d))
+            .unwrap();
+
+        let error = ActionError::NoTunnel { from, direction };
+        assert_eq!(game.act(Action::Move(direction)), Err(error));
+        assert_eq!(game.turn(), 0);
+        assert_eq!(game.player(), from);
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let mut a = Game::from_seed(20, Rules::preset("hard").unwrap(), 99);
+        let mut b = Game::from_seed(20, Rules::preset("hard").unwrap(), 99);
+        let json = |g: &Game| serde_json::to_string(g).unwrap();
+        assert_eq!(json(&a), json(&b));
+
+        assert_eq!(wander(&mut a, 50), wander(&mut b, 50));
+        assert_eq!(json(&a), json(&b));
+
+        let c = Game::from_seed(20, Rules::preset("hard").unwrap(), 100);
+        assert_ne!(a.maze().rooms, c.maze().rooms);
+    }
+
+    #[test]
+    fn shot_through_named_rooms() {
+        let mut game = empty_game(4);
+        let (first, second) = two_rooms_out(&game);
+        game.maze.wumpuses = vec![second];
+
+        let events = game.act(Action::ShootRooms(vec![first, second])).unwrap();
+        assert_eq!(events, vec![Event::WumpusKilled]);
+        assert!(game.won());
+    }
+
+    #[test]
+    fn crooked_and_missing_rooms_are_rejected() {
+        let mut game = empty_game(4);
+        let (first, second) = two_rooms_out(&game);
+        game.maze.wumpuses = vec![second];
+        let arrows = game.maze().arrows;
+
+        let crooked = Action::ShootRooms(vec![first, second, first]);
+        assert_eq!(game.act(crooked), Err(ActionError::TooCrooked));
+        let missing = Action::ShootRooms(vec![first, 1000]);
+        assert_eq!(game.act(missing), Err(ActionError::NoSuchRoom { room: 1000 }));
+
+        assert_eq!(game.maze().arrows, arrows);
+        assert_eq!(game.turn(), 0);
+    }
+
+    #[test]
+    fn flight_is_where_the_arrow_goes() {
+        for seed in 0..20 {
+            let mut game = empty_game(seed);
+            game.rules.recover_arrows = true;
+            let start = game.player();
+
+            // Aiming at a room that isn't next door sends the arrow down a random tunnel
+            let far = (0..game.maze().rooms.len())
+                .find(|&r| r != start && game.maze().rooms[start].iter().all(|&(n, _)| n != r))
+                .unwrap();
+            let shot = Action::ShootRooms(vec![far]);
+
+            let flight = game.flight(&shot).unwrap();
+            assert_eq!(game.flight(&shot), Some(flight.clone()));
+            game.act(shot).unwrap();
+            assert_eq!(game.maze().dropped.keys().collect::<Vec<_>>(), [&flight[0]]);
+        }
+
+        let game = empty_game(1);
+        let (first, _) = two_rooms_out(&game);
+        let direction = towards(&game, game.player(), first);
+        assert_eq!(game.flight(&Action::Shoot(vec![direction])), Some(vec![first]));
+        assert_eq!(game.flight(&Action::Move(direction)), None);
+    }
+
+    #[test]
+    fn missed_arrows_can_be_picked_up() {
+        let mut game = empty_game(5);
+        game.rules.recover_arrows = true;
+        game.rules.arrow_cost = crate::rules::ArrowCost::PerRoom;
+        let start = game.player();
+        let (first, second) = two_rooms_out(&game);
+        game.maze.wumpuses = (0..game.maze().rooms.len())
+            .filter(|&r| r != start && r != first && r != second)
+            .take(1)
+            .collect();
+        // Only moves when it's woken, so it stays put when it hears the arrow
+        game.set_behaviour(Arc::new(RunTo(0)));
+        let arrows = game.maze().arrows;
+
+        let events = game.act(Action::ShootRooms(vec![first, second])).unwrap();
+        assert_eq!(events[0], Event::ArrowMissed);
+        assert_eq!(game.maze().arrows, arrows - 2);
+        assert_eq!(game.maze().dropped.get(&second), Some(&2));
+
+        game.act(Action::Move(towards(&game, start, first))).unwrap();
+        let events = game.act(Action::Move(towards(&game, first, second))).unwrap();
+        assert_eq!(events[0], Event::FoundArrows { count: 2 });
+        assert_eq!(game.maze().arrows, arrows);
+        assert!(game.maze().dropped.is_empty());
+    }
+
+    #[test]
+    fn woken_wumpus_is_forgotten() {
+        let mut game = empty_game(7);
+        let rooms = game.maze().rooms.clone();
+        let start = game.player();
+        let near = |a: usize, b: usize| rooms[a].iter().any(|&(r, _)| r == b);
+
+        // Walk through a room next to the start into one that isn't, where the wumpus is. When
+        // it's woken it runs back to the room in between, which was ruled out earlier.
+        let (middle, wumpus) = rooms[start]
+            .iter()
+            .flat_map(|&(m, _)| rooms[m].iter().map(move |&(w, _)| (m, w)))
+            .find(|&(_, w)| w != start && !near(start, w))
+            .expect("the cave is bigger than one room's neighbours");
+        game.maze.wumpuses = vec![wumpus];
+        game.set_behaviour(Arc::new(RunTo(middle)));
+
+        game.act(Action::Move(towards(&game, start, middle))).unwrap();
+        let events = game.act(Action::Move(towards(&game, middle, wumpus))).unwrap();
+        assert_eq!(events, vec![Event::SmellWumpus]);
+        assert_eq!(game.maze().wumpuses, vec![middle]);
+
+        let status = game.knowledge().status(&rooms, middle, Hazard::Wumpus);
+        assert_ne!(status, Status::Absent);
+    }
+
+    #[test]
+    fn too_few_arrows_for_a_shot_ends_the_game() {
+        let mut game = empty_game(3);
+        game.rules.min_shot = 2;
+        game.rules.arrow_cost = crate::rules::ArrowCost::PerRoom;
+        game.maze.arrows = 3;
+
+        // Somewhere to put the wumpus where the arrow won't go
+        let start = game.player();
+        let (first, second) = two_rooms_out(&game);
+        game.maze.wumpuses = (0..game.maze().rooms.len())
+            .filter(|&r| r != start && r != first && r != second)
+            .take(1)
+            .collect();
+
+        let events = game.act(Action::ShootRooms(vec![first, second])).unwrap();
+        assert_eq!(events, vec![Event::ArrowMissed, Event::OutOfArrows]);
+        assert_eq!(game.maze().arrows, 1);
+        assert!(game.is_over());
+    }
+}
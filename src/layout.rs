@@ -0,0 +1,107 @@
+//! Graph layout for drawing caves as a whole
+//!
+//! The compass directions on tunnels don't say anything about where rooms actually are, so any
+//! picture of more than one room needs to decide that for itself. This uses a simple layered
+//! layout: rooms are put into rows by their distance from a starting room, and each row is then
+//! ordered to keep tunnels between rows as short as possible.
+
+use std::collections::VecDeque;
+
+// The number of passes used to order the rooms within each layer. Each pass looks at the layer
+// above and then the layer below.
+const ORDERING_PASSES: usize = 4;
+
+/// Positions each of the `n` nodes of a graph, returning `(x, y)` for each one
+///
+/// `y` is the layer the node is in, and `x` is its position within the layer. Every layer is
+/// centered on `x = 0`, so `y` is always a whole number but `x` is one only in layers with an odd
+/// number of nodes; otherwise it's half-way between two. Layers are counted from `root`; any nodes
+/// that can't be reached from it are laid out separately, below everything else.
+pub(crate) fn layered(n: usize, edges: &[(usize, usize)], root: usize) -> Vec<(f32, f32)> {
+    let mut neighbors = vec![Vec::new(); n];
+    for &(a, b) in edges {
+        if a != b {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+    }
+
+    // Assign the layers with a breadth-first search from the root, and then from each node
+    // that's still unreached
+    let mut layer_of = vec![None; n];
+    let mut layers: Vec<Vec<usize>> = Vec::new();
+    let starts = std::iter::once(root).chain(0..n).filter(|&r| r < n);
+    for start in starts {
+        if layer_of[start].is_some() {
+            continue;
+        }
+
+        let first = layers.len();
+        let mut queue = VecDeque::new();
+        layer_of[start] = Some(first);
+        queue.push_back(start);
+
+        while let Some(node) = queue.pop_front() {
+            let layer = layer_of[node].unwrap();
+            if layers.len() <= layer {
+                layers.push(Vec::new());
+            }
+            layers[layer].push(node);
+
+            for &next in &neighbors[node] {
+                if layer_of[next].is_none() {
+                    layer_of[next] = Some(layer + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    // Order each layer by the average position of each node's neighbors in the adjacent layer,
+    // sweeping down and then back up
+    let mut pos = vec![0.0; n];
+    let set_positions = |layer: &[usize], pos: &mut Vec<f32>| {
+        for (i, &node) in layer.iter().enumerate() {
+            pos[node] = i as f32;
+        }
+    };
+
+    for layer in &layers {
+        set_positions(layer, &mut pos);
+    }
+
+    for _ in 0..ORDERING_PASSES {
+        let down = 1..layers.len();
+        let up = (0..layers.len().saturating_sub(1)).rev();
+
+        for (l, adjacent) in down.map(|l| (l, l - 1)).chain(up.map(|l| (l, l + 1))) {
+            let barycenter = |node: usize| {
+                let (sum, count) = neighbors[node]
+                    .iter()
+                    .filter(|&&m| layer_of[m] == Some(adjacent))
+                    .fold((0.0, 0), |(s, c), &m| (s + pos[m], c + 1));
+
+                if count == 0 {
+                    pos[node]
+                } else {
+                    sum / count as f32
+                }
+            };
+
+            let mut keyed: Vec<_> = layers[l].iter().map(|&m| (barycenter(m), m)).collect();
+            keyed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap().then(a.1.cmp(&b.1)));
+            layers[l] = keyed.into_iter().map(|(_, m)| m).collect();
+            set_positions(&layers[l], &mut pos);
+        }
+    }
+
+    let mut coords = vec![(0.0, 0.0); n];
+    for (y, layer) in layers.iter().enumerate() {
+        let offset = (layer.len() as f32 - 1.0) / 2.0;
+        for (x, &node) in layer.iter().enumerate() {
+            coords[node] = (x as f32 - offset, y as f32);
+        }
+    }
+
+    coords
+}
@@ -0,0 +1,235 @@
+//! Caves designed by hand, read from level files
+//!
+//! A level is written as TOML. Each room lists its three tunnels as the room they lead to and the
+//! direction they leave in, in the order they're stored in the `Room`:
+//!
+//! ```toml
+//! start = 0
+//! arrows = 3
+//! bats = [2]
+//! pits = []
+//! wumpuses = [3]
+//!
+//! [rooms]
+//! 0 = [[1, "west"], [2, "north"], [3, "east"]]
+//! 1 = [[0, "east"], [2, "west"], [3, "north"]]
+//! 2 = [[0, "south"], [1, "east"], [3, "west"]]
+//! 3 = [[0, "west"], [1, "south"], [2, "east"]]
+//! ```
+//!
+//! `start` defaults to room 0, `arrows` to the number the rules give, and each list of hazards to
+//! nothing - except that there must be at least one wumpus. The cave itself has to pass
+//! `Maze::validate`.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::maze::{Direction, Hazards, Maze, Room};
+
+/// The reasons that a level file can't be used
+#[derive(Debug)]
+pub enum LevelError {
+    /// The file couldn't be parsed
+    Format(toml::de::Error),
+    /// The file was parsed, but doesn't describe a valid cave
+    Invalid(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LevelFile {
+    #[serde(default)]
+    start: usize,
+    arrows: Option<i32>,
+    #[serde(default)]
+    bats: Vec<usize>,
+    #[serde(default)]
+    pits: Vec<usize>,
+    #[serde(default)]
+    wumpuses: Vec<usize>,
+    rooms: BTreeMap<String, Vec<(usize, String)>>,
+}
+
+fn parse_direction(s: &str) -> Option<Direction> {
+    match s.to_lowercase().as_str() {
+        "north" => Some(Direction::North),
+        "south" => Some(Direction::South),
+        "east" => Some(Direction::East),
+        "west" => Some(Direction::West),
+        _ => None,
+    }
+}
+
+impl Maze {
+    /// Reads a cave from a level file, checking that it keeps to the same rules as a generated
+    /// cave. `arrows` is used if the level doesn't give the number of arrows itself.
+    pub fn from_level(s: &str, arrows: i32) -> Result<Self, LevelError> {
+        let level: LevelFile = toml::from_str(s).map_err(LevelError::Format)?;
+        let invalid = |msg: String| Err(LevelError::Invalid(msg));
+
+        let n = level.rooms.len();
+        if n < 4 {
+            return invalid(format!("a level needs at least 4 rooms, but this one has {}", n));
+        }
+
+        // Rooms can be listed in any order, but have to be numbered from 0 with none missing
+        let mut rooms: Vec<Option<Room>> = vec![None; n];
+        for (key, tunnels) in &level.rooms {
+            let idx = match key.parse::<usize>() {
+                Ok(i) if i < n => i,
+                _ => {
+                    return invalid(format!(
+                        "'{}' isn't a valid room number: with {} rooms, they must be numbered \
+                         from 0 to {}",
+                        key,
+                        n,
+                        n - 1
+                    ))
+                }
+            };
+
+            if rooms[idx].is_some() {
+                return invalid(format!("room {} is listed more than once", idx));
+            } else if tunnels.len() != 3 {
+                return invalid(format!(
+                    "room {} has {} tunnels, but every room must have exactly 3",
+                    idx,
+                    tunnels.len()
+                ));
+            }
+
+            let mut room = [(0, Direction::North); 3];
+            for (i, (to, dir)) in tunnels.iter().enumerate() {
+                let dir = match parse_direction(dir) {
+                    Some(d) => d,
+                    None => {
+                        return invalid(format!(
+                            "room {} has a tunnel going '{}', which should be north, south, east \
+                             or west",
+                            idx, dir
+                        ))
+                    }
+                };
+
+                if *to >= n {
+                    return invalid(format!(
+                        "the tunnel {} from room {} leads to room {}, which doesn't exist",
+                        dir, idx, to
+                    ));
+                } else if *to == idx {
+                    return invalid(format!(
+                        "the tunnel {} from room {} leads back into the same room",
+                        dir, idx
+                    ));
+                } else if room[..i].iter().any(|&(_, d)| d == dir) {
+                    return invalid(format!("room {} has more than one tunnel going {}", idx, dir));
+                }
+                room[i] = (*to, dir);
+            }
+            rooms[idx] = Some(room);
+        }
+
+        let rooms: Vec<Room> = rooms.into_iter().map(Option::unwrap).collect();
+
+        let places = [
+            ("the start", std::slice::from_ref(&level.start)),
+            ("bats", &level.bats[..]),
+            ("a pit", &level.pits[..]),
+            ("a wumpus", &level.wumpuses[..]),
+        ];
+        for (what, list) in places.iter() {
+            if let Some(r) = list.iter().find(|&&r| r >= n) {
+                return invalid(format!("{} is in room {}, which doesn't exist", what, r));
+            }
+        }
+
+        let lists = [("bats", &level.bats), ("pits", &level.pits), ("wumpuses", &level.wumpuses)];
+        for (name, list) in lists.iter() {
+            let repeated = list.iter().enumerate().find(|&(i, r)| list[..i].contains(r));
+            if let Some((_, r)) = repeated {
+                return invalid(format!("room {} is listed more than once in {}", r, name));
+            }
+        }
+
+        if let Some((what, _)) = places[1..].iter().find(|(_, list)| list.contains(&level.start)) {
+            return invalid(format!(
+                "the starting room ({}) can't have {} in it",
+                level.start, what
+            ));
+        } else if level.wumpuses.is_empty() {
+            return invalid("there must be at least one wumpus".into());
+        }
+
+        let arrows = level.arrows.unwrap_or(arrows);
+        if arrows <= 0 {
+            return invalid("the player must start with at least one arrow".into());
+        }
+
+        let maze = Self {
+            rooms,
+            bats: level.bats,
+            pits: level.pits,
+            wumpuses: level.wumpuses,
+            arrows,
+            dropped: BTreeMap::new(),
+            start: level.start,
+        };
+
+        // The rest of the checks are the same as for any other cave: that every tunnel has one
+        // coming back, and that every room can be reached
+        maze.validate().map_err(|e| LevelError::Invalid(e.to_string()))?;
+        Ok(maze)
+    }
+
+    /// The number of each hazard in the cave
+    pub fn hazards(&self) -> Hazards {
+        Hazards {
+            bats: self.bats.len(),
+            pits: self.pits.len(),
+            wumpuses: self.wumpuses.len(),
+        }
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Format(e) => write!(f, "malformed level: {}", e),
+            LevelError::Invalid(msg) => write!(f, "invalid level: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOMS: &str = r#"
+        [rooms]
+        0 = [[1, "west"], [2, "north"], [3, "east"]]
+        1 = [[0, "east"], [2, "west"], [3, "north"]]
+        2 = [[0, "south"], [1, "east"], [3, "west"]]
+        3 = [[0, "west"], [1, "south"], [2, "east"]]
+    "#;
+
+    #[test]
+    fn reads_a_level() {
+        let maze = Maze::from_level(&format!("bats = [2]\nwumpuses = [3]\n{}", ROOMS), 5).unwrap();
+        assert_eq!(maze.rooms, Maze::quad().rooms);
+        assert_eq!((maze.bats, maze.wumpuses, maze.arrows), (vec![2], vec![3], 5));
+    }
+
+    #[test]
+    fn repeated_hazards_are_rejected() {
+        let level = format!("wumpuses = [3, 3]\n{}", ROOMS);
+        match Maze::from_level(&level, 5) {
+            Err(LevelError::Invalid(msg)) => {
+                assert_eq!(msg, "room 3 is listed more than once in wumpuses")
+            }
+            other => panic!("expected the level to be invalid, got {:?}", other.map(|_| ())),
+        }
+    }
+}
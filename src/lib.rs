@@ -0,0 +1,53 @@
+//! A modified version of "Hunt the Wumpus", with randomly generated caves
+//!
+//! The [`Game`] engine doesn't do any I/O itself: it takes [`Action`]s from whatever front-end is
+//! driving it and returns the [`Event`]s that resulted. The terminal game in `main.rs` is one
+//! such front-end.
+
+mod behaviour;
+mod daily;
+mod deduce;
+mod display;
+mod export;
+mod game;
+mod layout;
+mod level;
+mod map;
+mod maze;
+mod notes;
+mod render;
+mod replay;
+mod rng;
+mod rules;
+mod save;
+mod scores;
+mod simulate;
+mod solve;
+mod stats;
+mod validate;
+
+pub use behaviour::{Behaviour, Cave, Coward, Hunter, Profile, Sleeper, Wanderer};
+pub use daily::{
+    daily_game, daily_rules, daily_seed, share, DailyLog, DailyResult, Date, DAILY_ROOMS,
+    DAILY_RULES, DAILY_VERSION,
+};
+pub use deduce::{Hazard, Knowledge, Status};
+pub use display::Annotation;
+pub use export::ExportFormat;
+pub use game::{Action, ActionError, Event, Game};
+pub use level::LevelError;
+pub use maze::{Direction, Hazards, Maze, Orientation, Room};
+pub use notes::Marker;
+pub use render::{Ascii, Color, Highlight, Renderer, Style, Unicode};
+pub use replay::{Playback, PlaybackError, Replay, Turn};
+pub use rng::GameRng;
+pub use rules::{ArrowCost, Placement, Rules, RulesError};
+pub use save::{SaveError, SAVE_VERSION};
+pub use scores::{HighScores, Score, Table, SCORES_VERSION, TABLE_SIZE};
+pub use simulate::{simulate, Outcome, Policy, Tally, TURN_LIMIT};
+pub use solve::{Decision, Solver};
+pub use stats::{Record, Stats, STATS_VERSION};
+pub use validate::MazeError;
+
+pub const WUMPUS_MOVE_PROB: f32 = 0.75;
+pub const STARTING_ARROWS: i32 = 5;
@@ -1,712 +1,1207 @@
-extern crate rand;
-use rand::Rng;
-
-use std::io::Write;
-use std::process::exit;
+use std::collections::BTreeMap;
 use std::env;
-
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum Direction {
-    North,
-    South,
-    East,
-    West,
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, IsTerminal, Stdin, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use clap::Parser;
+use wumpus::Direction::{East, North, South, West};
+use wumpus::{
+    daily_game, share, simulate, Action, ActionError, DailyLog, DailyResult, Date, Direction, Event,
+    Game, Highlight, HighScores, Outcome, Renderer, Replay, SaveError, Solver, Stats, Style, Turn,
+    DAILY_ROOMS, DAILY_RULES, TURN_LIMIT,
+};
+
+mod cli;
+mod tui;
+
+use cli::{
+    CaveArgs, Cli, Command, DailyArgs, ExportArgs, PlayArgs, ReplayArgs, ScoresArgs, SimulateArgs,
+    SolveArgs,
+};
+
+// The files kept in the data directory
+const SCORES_FILE: &str = "scores.json";
+const STATS_FILE: &str = "stats.json";
+const DAILY_FILE: &str = "daily.json";
+
+// The width assumed for the end-of-game map when the terminal's size can't be found
+const DEFAULT_WIDTH: usize = 80;
+
+// Reads a single trimmed line from stdin after printing the prompt
+//
+// Reaching the end of the input is treated the same as entering 'quit'.
+fn prompt(stdin: &Stdin, msg: &str) -> String {
+    print!("{}", msg);
+    std::io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    if stdin.read_line(&mut input).unwrap() == 0 {
+        println!();
+        return "quit".into();
+    }
+    input.trim().into()
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum Orientation {
-    NorthSouth,
-    EastWest,
+fn save_game(game: &Game, path: &str) -> Result<(), SaveError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    game.save(&mut writer)?;
+    writer.flush()?;
+    Ok(())
 }
 
-type Room = [(usize, Direction); 3];
-
-#[derive(Clone, Debug)]
-struct Maze {
-    rooms: Vec<Room>,
-    bats: usize,
-    pit: usize,
-    wumpus: usize,
+fn load_game(path: &str) -> Result<Game, SaveError> {
+    Game::load(BufReader::new(File::open(path)?))
+}
 
-    arrows: i32,
+fn load_replay(path: &str) -> Result<Replay, SaveError> {
+    Replay::load(BufReader::new(File::open(path)?))
 }
 
-use Direction::{North, South, East, West};
-use Orientation::{NorthSouth, EastWest};
+// Records each turn of the game, rewriting the replay file every time (if there is one) so that
+// nothing is lost if the game is cut short
+struct Recorder {
+    // Where to write the replay. Without this, it's only kept in memory.
+    path: Option<String>,
+    replay: Replay,
+    // In practice games, the game as it was before each turn, so that turns can be taken back
+    undo: Option<Vec<Game>>,
+}
 
-impl Direction {
-    // This takes `self` as a receiver instead of `&self` because we
-    // implemented copy.
-    fn opposite(self) -> Direction {
-        match self {
-            North => South,
-            South => North,
-            East => West,
-            West => East,
+impl Recorder {
+    fn new(game: &Game, path: Option<String>, practice: bool) -> Self {
+        Self {
+            path,
+            replay: Replay::new(game),
+            undo: if practice { Some(Vec::new()) } else { None },
         }
     }
 
-    // Finds a direction that isn't present in this list
-    /*
-    fn not_present(ls: &[Self]) -> Option<Direction> {
-        let mut present = [false; 4];
-
-        for d in ls.iter() {
-            match d {
-                Self::North => present[0] = true,
-                Self::South => present[1] = true,
-                Self::East => present[2] = true,
-                Self::West => present[3] = true,
-            }
+    // Records a turn, given the game as it was before the turn was taken
+    fn record(&mut self, before: Game, action: Action, events: &[Event]) -> Result<(), String> {
+        if let Some(undo) = self.undo.as_mut() {
+            undo.push(before);
         }
+        self.replay.record(action, events);
 
-        let from_int = |i| match i {
-            0 => Self::North,
-            1 => Self::South,
-            2 => Self::East,
-            3 => Self::West,
-            _ => unreachable!(),
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
         };
-
-        present
-            .into_iter()
-            .enumerate()
-            .filter(|(_, &p)| !p)
-            .map(|(i, _)| i)
-            .collect::<Vec<_>>()
-            .pop()
-            .map(from_int)
+        let write = || self.replay.save(BufWriter::new(File::create(path)?));
+        write().map_err(|e| format!("Failed to write the replay to '{}': {}", path, e))
     }
-    */
 
-    // Gives the orientation
-    fn orientation(&self) -> Orientation {
-        match self {
-            North => NorthSouth,
-            South => NorthSouth,
-            East => EastWest,
-            West => EastWest,
+    // Starts recording a different game from scratch
+    fn restart(&mut self, game: &Game) {
+        self.replay = Replay::new(game);
+        if let Some(undo) = self.undo.as_mut() {
+            undo.clear();
         }
     }
-}
 
-impl Orientation {
-    fn major(&self) -> Direction {
-        match self {
-            NorthSouth => North,
-            EastWest => East,
-        }
+    fn can_rewind(&self) -> bool {
+        self.undo.is_some()
     }
 
-    fn minor(&self) -> Direction {
-        match self {
-            NorthSouth => South,
-            EastWest => West,
+    // Takes back the given number of turns, returning the game as it was before them. The
+    // replay file catches up on the next turn.
+    fn rewind(&mut self, turns: usize) -> Result<Game, String> {
+        let undo = self
+            .undo
+            .as_mut()
+            .ok_or("Turns can only be taken back in practice games (play --practice).")?;
+        match undo.len() {
+            0 => return Err("There aren't any turns to take back.".into()),
+            1 if turns > 1 => return Err("There's only one turn to take back.".into()),
+            n if turns > n => return Err(format!("There are only {} turns to take back.", n)),
+            _ => (),
         }
+
+        let mut game = undo.split_off(undo.len() - turns).swap_remove(0);
+        game.set_rewound();
+        self.replay.turns.truncate(self.replay.turns.len() - turns);
+        Ok(game)
     }
 }
 
-impl Maze {
-    fn quad() -> Self {
-        let rooms = vec![
-            [(1, West), (2, North), (3, East)],
-            [(0, East), (2, West), (3, North)],
-            [(0, South), (1, East), (3, West)],
-            [(0, West), (1, South), (2, East)],
-        ];
+// Splits a line of input into the command, in lower case, and the rest
+fn split_command(line: &str) -> (String, &str) {
+    match line.find(char::is_whitespace) {
+        Some(i) => (line[..i].to_lowercase(), line[i..].trim()),
+        None => (line.to_lowercase(), ""),
+    }
+}
 
-        Self {
-            rooms,
-            bats: 0,
-            pit: 0,
-            wumpus: 0,
-            arrows: STARTING_ARROWS,
-        }
-    }
-
-    // Expands the maze to include more rooms at the given index
-    fn expand(&mut self, idx: usize, rand_bool: bool) {
-        fn link_idx(idx: usize, r: Room) -> usize {
-            r.iter().position(|(i,_)| i == &idx).unwrap()
-        }
-
-        // It may be helpful to remember:
-        //   self.rooms[idx] = r0
-        //   r0 = [(r0[0].0, r0[0].1), (r0[1].0, r0[1].1), (r0[2].0, r0[2].1)]
-        // Generally, we're taking the connections to r0 and redirecting them
-        // to other nodes.
-        let r0 = self.rooms[idx];
-
-        // The indexes of the tunnel to r0 in the rooms it links to
-        let r0_from_others = [
-            link_idx(idx, self.rooms[r0[0].0]),
-            link_idx(idx, self.rooms[r0[1].0]),
-            link_idx(idx, self.rooms[r0[2].0]),
-        ];
-
-        // We're creating two new rooms: r1 and r2.
-        let r1_idx = self.rooms.len();
-        let r2_idx = r1_idx + 1;
-
-        // indexes of the major/minor directions
-        let (fst_maj, fst_min, snd) = {
-            // directions
-            let ds = r0.iter().map(|(_,d)| d).collect::<Vec<_>>();
-            
-            // check against the other two
-            let o = ds[0].orientation();
-
-            if ds[1].orientation() == o {
-                if o.major() == *ds[0] {
-                    (0, 1, 2)
-                } else {
-                    (1, 0, 2)
-                }
-            } else if ds[2].orientation() == o {
-                if o.major() == *ds[0] {
-                    (0, 2, 1)
-                } else {
-                    (2, 0, 1)
-                }
-            } else {
-                if ds[1].orientation().major() == *ds[1] {
-                    (1, 2, 0)
-                } else {
-                    (2, 1, 0)
-                }
+// Handles 'undo' and 'rewind <turns>', returning whether any turns were taken back
+fn take_back(game: &mut Game, recorder: &mut Recorder, cmd: &str, arg: &str) -> bool {
+    let turns = match cmd {
+        "undo" => 1,
+        _ => match arg.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                println!("Usage: rewind <turns> (e.g. 'rewind 3')");
+                return false;
             }
-        };
-
-        let fst_or = r0[fst_maj].1.orientation();
-        let snd_d = r0[snd].1;
+        },
+    };
 
-        let r0_new = [
-            (r0[fst_maj].0, fst_or.major()),
-            (r1_idx, fst_or.minor()),
-            (r2_idx, snd_d),
-        ];
-        
-        // we don't need to set this existing room because it's already there.
+    match recorder.rewind(turns) {
+        Ok(g) => {
+            *game = g;
+            match turns {
+                1 => println!("Took back a turn."),
+                n => println!("Took back {} turns.", n),
+            }
+            true
+        }
+        Err(e) => {
+            println!("{}", e);
+            false
+        }
+    }
+}
 
-        let r2_r1_d = if rand_bool {
-            fst_or.major()
-        } else {
-            snd_d
-        };
+fn parse_direction(input: &str) -> Option<Direction> {
+    match input {
+        "left" | "west" | "w" => Some(West),
+        "right" | "east" | "e" => Some(East),
+        "up" | "north" | "n" => Some(North),
+        "down" | "south" | "s" => Some(South),
+        _ => None,
+    }
+}
 
-        let r1 = [
-            (idx, fst_or.major()),
-            (r0[snd].0, snd_d),
-            (r2_idx, r2_r1_d.opposite()),
-        ];
+// Parses a shot given all at once, either as the rooms for the arrow to go through or as the
+// direction to take out of each room
+fn parse_shot(arg: &str) -> Option<Action> {
+    let words: Vec<&str> = arg.split_whitespace().collect();
 
-        self.rooms[r0[snd].0][r0_from_others[snd]] = (r1_idx, snd_d.opposite());
+    if let Ok(rooms) = words.iter().map(|w| w.parse()).collect::<Result<Vec<usize>, _>>() {
+        return Some(Action::ShootRooms(rooms));
+    }
 
-        let r2 = [
-            (r1_idx, r2_r1_d), // This just continues from the last of r1. Can be chosen
-            (r0[fst_min].0, fst_or.minor()),
-            (idx, snd_d.opposite()),
-        ];
+    words
+        .iter()
+        .map(|w| parse_direction(&w.to_lowercase()))
+        .collect::<Option<Vec<_>>>()
+        .map(Action::Shoot)
+}
 
-        self.rooms[r0[fst_min].0][r0_from_others[fst_min]] = (r2_idx, fst_or.major());
+fn print_events(events: &[Event]) {
+    for e in events {
+        println!("{}", e);
+    }
+}
 
-        // set all of the rooms
-        self.rooms[idx] = r0_new;
-        self.rooms.push(r1);
-        self.rooms.push(r2);
+// Picks the renderer from the environment: ASCII for terminals that are known to be limited,
+// and colour wherever it's likely to work and hasn't been turned off
+fn detect_style() -> Style {
+    let term = env::var("TERM").unwrap_or_default();
+    let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+
+    if term.is_empty() || term == "dumb" {
+        Style::Ascii
+    } else if no_color || !std::io::stdout().is_terminal() {
+        Style::Unicode
+    } else {
+        Style::Color
     }
+}
 
-    // The total number will be 4 + (2 * adds)
-    fn generate(adds: u32) -> Self {
-        let mut rng = rand::thread_rng();
+// Draws a room that an arrow is passing through, highlighting the rooms it's already been in
+fn render_shot(game: &Game, renderer: &dyn Renderer, idx: usize, path: &[usize]) -> String {
+    game.maze().render_room(idx, renderer, |r| {
+        let mut note = game.annotate(r);
+        if r != game.player() && (r == idx || path.contains(&r)) {
+            note.highlight = Highlight::Arrow;
+        }
+        note
+    })
+}
 
-        let mut maze = Self::quad();
+// Asks the player for the distance and path of a shot, showing each room along the way
+fn read_shot(game: &Game, renderer: &dyn Renderer, stdin: &Stdin) -> Action {
+    let maze = game.maze();
 
-        for _ in 0 .. adds {
-            let idx = (rng.gen::<f32>() * maze.rooms.len() as f32) as usize;
-            maze.expand(idx, rng.gen());
-        }
+    let dist = loop {
+        let input = prompt(stdin, "How far do you want to shoot? ");
 
-        maze.bats = 1 + (rng.gen::<f32>() * (maze.rooms.len() -1) as f32) as usize;
-        
-        maze.pit = loop {
-            let i = 1 + (rng.gen::<f32>() * (maze.rooms.len() -1) as f32) as usize;
-            if i != maze.bats {
-                break i;
+        // attempt to parse the distance
+        let dist: i32 = match input.parse() {
+            Ok(d) => d,
+            Err(_) => {
+                println!("Please enter a number.");
+                continue;
             }
         };
 
-        maze.wumpus = if adds != 0 {
-            loop {
-                let i = 1 + (rng.gen::<f32>() * (maze.rooms.len() -1) as f32) as usize;
+        let (min, max) = (game.rules().min_shot, game.rules().max_shot);
+        if dist < min as i32 || dist > max as i32 {
+            println!("{}", ActionError::BadShotDistance { min, max });
+            continue;
+        } else if game.rules().shot_cost(dist) > maze.arrows {
+            println!("{}", ActionError::NotEnoughArrows { have: maze.arrows });
+            continue;
+        }
+
+        break dist;
+    };
 
-                // guarantee that the wumpus isn't next to any of the starting
-                // squares
-                if !maze.rooms[0].iter().any(|(r,_)| r == &i) {
-                    break i;
+    let mut path = Vec::new();
+    let mut rooms = Vec::new();
+    let mut idx = game.player();
+    for _ in 0..dist {
+        println!("{}", render_shot(game, renderer, idx, &rooms));
+
+        idx = loop {
+            let input = prompt(stdin, "Pick a direction to continue the shot: ").to_lowercase();
+
+            let direction = match parse_direction(&input) {
+                Some(d) => d,
+                None if input == "quit" || input == "exit" => exit(0),
+                None => {
+                    println!("Directions should be left/right/up/down or north/south/east/west");
+                    println!("Enter 'quit' to quit");
+                    continue;
                 }
+            };
+
+            let r = maze.rooms[idx];
+            if let Some(&(next, _)) = r.iter().find(|(_, d)| d == &direction) {
+                path.push(direction);
+                rooms.push(next);
+                break next;
             }
-        } else {
-            1 + (rng.gen::<f32>() * (maze.rooms.len() -1) as f32) as usize
+
+            println!("Can't shoot that way, there's a wall!");
         };
+    }
 
-        maze
-    }
-
-    // Currently only works with up to three-digit numbers
-    fn display_room(&self, room_idx: usize) {
-        // the maximum length of the room numbers
-        // This is for a later improvement
-        //
-        // let max_length = self.rooms[idx].iter()
-        //     .map(|(i,_)| (*i as f32).log10().ceil() as usize)
-        //     .max()
-        //     .unwrap();
-
-        // Typical display, with terminal border at the indent of the first '/'
-        /*
-                          .                
-                      ╔═╝ . ╚═╗            
-                      ╝       ║            
-                     ..   7   ║            
-                      ╗       ║            
-              .       ╚═╗   ╔═╝       .    
-          ╔═╝ . ╚═╗  ╔══╝   ╚══╗  ╔═╝ . ╚═╗
-          ╝       ╚══╝         ╚══╝       ║
-         ..  37          You         61   ║
-          ╗       ╔══╗    3    ╔══╗       ║
-          ╚═══════╝  ╚═════════╝  ╚═╗ . ╔═╝
-                                      .    
-        */
-
-        // magic numbers, based on above
-        const WIDTH: usize = 35;
-        const HEIGHT: usize = 17;
-        const SIZE: usize = (WIDTH + 1) * HEIGHT;
-
-        // row counted down from the top, starting at 0
-        fn idx(row: usize, col: usize) -> usize {
-            // add one for newline
-            (WIDTH + 1) * row + col
-        }
-
-        // new strings should be from top down
-        fn overwrite(data: &mut [char; SIZE], new: Vec<String>, row: usize, col: usize) {
-            for (r, s) in new.iter().enumerate() {
-                let chars = s.chars().collect::<Vec<_>>();
-                let i = idx(row + r, col);
-                data[i .. i + chars.len()].copy_from_slice(&chars);
+    Action::Shoot(path)
+}
+
+// Handles the commands for marking rooms, returning whether the room should be redrawn
+fn notes_command(game: &mut Game, cmd: &str, arg: &str) -> bool {
+    let (room, rest) = match arg.find(char::is_whitespace) {
+        Some(i) => (&arg[..i], arg[i..].trim()),
+        None => (arg, ""),
+    };
+    let room = room.parse::<usize>().ok().filter(|&r| r < game.maze().rooms.len());
+
+    match (cmd, room) {
+        ("mark", Some(room)) if !rest.is_empty() => {
+            for m in rest.split(',').map(str::trim).filter(|m| !m.is_empty()) {
+                game.mark(room, m.parse().unwrap());
+            }
+            true
+        }
+        ("mark", _) => {
+            println!("Usage: mark <room> <note>, where the note can be safe, pit, bats or wumpus,");
+            println!("with a '?' if you aren't sure (e.g. 'mark 12 pit?'), or anything else.");
+            false
+        }
+        ("unmark", Some(room)) => {
+            game.unmark(room);
+            true
+        }
+        ("unmark", None) => {
+            println!("Usage: unmark <room>");
+            false
+        }
+        ("automark", _) => {
+            let on = match arg {
+                "on" => true,
+                "off" => false,
+                _ => !game.auto_mark(),
+            };
+            game.set_auto_mark(on);
+            println!("Automatic marking is {}.", if on { "on" } else { "off" });
+            true
+        }
+        _ => {
+            let notes = game.notes();
+            if notes.is_empty() {
+                println!("You haven't marked any rooms.");
+            }
+            for (room, markers) in notes {
+                let markers = markers.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+                let auto = if game.player_markers(room).is_empty() { " (auto)" } else { "" };
+                println!("{:>5}: {}{}", room, markers.join(", "), auto);
             }
+            false
         }
+    }
+}
 
-        // `top_bar` includes the corners and (if it's not the center) the
-        // line above
-        //
-        // Vector has length two
-        fn top_bar(r: Room, center: bool) -> Vec<String> {
-            let has_room_above = r.iter().any(|(_,d)| d == &North);
+// Commands that can be given while a replay is playing
+enum Control {
+    TogglePause,
+    Step,
+    Faster,
+    Slower,
+    Quit,
+}
 
-            if center {
-                if has_room_above {
-                    vec![
-                        " ╚═╗   ╔═╝ ".into(),
-                        "╔══╝   ╚══╗".into(),
-                    ]
-                } else {
-                    vec![
-                        "".into(),
-                        "╔═════════╗".into(),
-                    ]
-                }
-            } else {
-                if has_room_above {
-                    vec![
-                        "    .    ".into(),
-                        "╔═╝ . ╚═╗".into(),
-                    ]
-                } else {
-                    vec![
-                        "         ".into(),
-                        "╔═══════╗".into(),
-                    ]
-                }
+// Reads playback controls from stdin on a separate thread, so that the replay can keep playing
+// while waiting for them
+fn spawn_controls() -> Receiver<Control> {
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let stdin = std::io::stdin();
+        for line in stdin.lock().lines() {
+            let control = match line.unwrap_or_default().trim() {
+                "" | "p" | "pause" => Control::TogglePause,
+                "s" | "step" => Control::Step,
+                "+" | "f" | "faster" => Control::Faster,
+                "-" | "slower" => Control::Slower,
+                "q" | "quit" | "exit" => Control::Quit,
+                _ => continue,
+            };
+
+            if tx.send(control).is_err() {
+                return;
             }
         }
+    });
 
-        fn bot_bar(r: Room, center: bool) -> Vec<String> {
-            let has_room_below = r.iter().any(|(_,d)| d == &South);
+    rx
+}
 
-            if center {
-                if has_room_below {
-                    vec![
-                        "╚══╗   ╔══╝".into(),
-                        " ╔═╝   ╚═╗ ".into(),
-                    ]
-                } else {
-                    vec!["╚═════════╝".into()]
-                }
-            } else {
-                if has_room_below {
-                    vec![
-                        "╚═╗ . ╔═╝".into(),
-                        "    .    ".into(),
-                    ]
-                } else {
-                    vec![
-                        "╚═══════╝".into(),
-                        "         ".into(),
-                    ]
-                }
-            }
+// Plays back a single turn of the replay, drawing the same frames that the player saw
+fn show_turn(renderer: &dyn Renderer, before: &Game, after: &Game, number: usize, turn: &Turn) {
+    let (action, events) = (&turn.action, &turn.events);
+    println!("Turn {}: {}", number, action);
+
+    // The game from before the turn picks any random tunnels the same way it did when the turn
+    // was played, so a shot at named rooms flies the same way again
+    if let Some(rooms) = before.flight(action) {
+        let mut idx = before.player();
+        for (i, &next) in rooms.iter().enumerate() {
+            println!("{}", render_shot(before, renderer, idx, &rooms[..i]));
+            idx = next;
         }
+    }
 
-        // doesn't include top/bottom bar
-        // Each string is of width two
-        fn left_side(r: Room, center: bool) -> Vec<String> {
-            let has_room_left = r.iter().any(|(_,d)| d == &West);
+    for e in events.iter().filter(|e| matches!(e, Event::BatsCarriedYou { .. })) {
+        println!("{}", e);
+    }
+    println!("{}", after.render_room(renderer));
+    for e in events.iter().filter(|e| !matches!(e, Event::BatsCarriedYou { .. })) {
+        println!("{}", e);
+    }
+}
 
-            if center {
-                if has_room_left {
-                    vec![
-                        "╚══╝".into(),
-                        "    ".into(),
-                        "╔══╗".into(),
-                    ]
-                } else {
-                    vec![
-                        "   ║".into(),
-                        "   ║".into(),
-                        "   ║".into(),
-                    ]
-                }
-            } else {
-                if has_room_left {
-                    vec![
-                        " ╝".into(),
-                        "..".into(),
-                        " ╗".into(),
-                    ]
-                } else {
-                    vec![
-                        " ║".into(),
-                        " ║".into(),
-                        " ║".into(),
-                    ]
+// Handles 'wumpus replay'
+fn run_replay(args: ReplayArgs) -> Result<(), String> {
+    let mut delay = Duration::from_secs_f64(args.speed);
+    let mut paused = args.paused;
+
+    let replay =
+        load_replay(&args.file).map_err(|e| format!("failed to load '{}': {}", args.file, e))?;
+
+    println!("Playing back {} turns.", replay.turns.len());
+    println!("Press enter to pause or resume, 's' to step while paused, '+'/'-' to change speed,");
+    println!("and 'q' to stop.");
+
+    let renderer = args.style.unwrap_or_else(detect_style).renderer();
+    let controls = spawn_controls();
+    let mut playback = replay.playback();
+
+    println!("{}", playback.game().render_room(renderer));
+    print_events(&playback.game().senses());
+
+    loop {
+        let control = if paused {
+            // If stdin is closed, there's no way to resume - so we just carry on
+            controls.recv().ok()
+        } else {
+            match controls.recv_timeout(delay) {
+                Ok(c) => Some(c),
+                Err(RecvTimeoutError::Timeout) => Some(Control::Step),
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(delay);
+                    Some(Control::Step)
                 }
             }
+        };
+
+        match control {
+            Some(Control::TogglePause) => {
+                paused = !paused;
+                println!("{}", if paused { "Paused." } else { "Resumed." });
+                continue;
+            }
+            Some(Control::Faster) => {
+                delay /= 2;
+                continue;
+            }
+            Some(Control::Slower) => {
+                delay *= 2;
+                continue;
+            }
+            Some(Control::Quit) => return Ok(()),
+            Some(Control::Step) => (),
+            None => paused = false,
         }
 
-        fn right_side(r: Room, center: bool) -> Vec<String> {
-            let has_room_right = r.iter().any(|(_,d)| d == &East);
+        let before = playback.game().clone();
+        match playback.step() {
+            None => break,
+            Some(Ok(turn)) => show_turn(
+                renderer,
+                &before,
+                playback.game(),
+                playback.turns_done(),
+                turn,
+            ),
+            Some(Err(e)) => return Err(format!("stopping playback: {}", e)),
+        }
+    }
 
-            if center {
-                if has_room_right {
-                    vec![
-                        "╚══╝".into(),
-                        "    ".into(),
-                        "╔══╗".into(),
-                    ]
-                } else {
-                    vec![
-                        "║".into(),
-                        "║".into(),
-                        "║".into(),
-                    ]
+    println!("End of replay.");
+    Ok(())
+}
+
+// Lets the solver play a game to the end, returning how it ended and how many turns it took
+fn solve_game(mut game: Game, explain: bool) -> (Outcome, u32) {
+    let solver = Solver;
+
+    if explain {
+        print_events(&game.senses());
+    }
+
+    while !game.is_over() && game.turn() < TURN_LIMIT {
+        let decision = match solver.decide(&game) {
+            Some(d) => d,
+            None => {
+                if explain {
+                    println!();
+                    println!("There aren't enough arrows left to win.");
                 }
-            } else {
-                if has_room_right {
-                    vec![
-                        "╚ ".into(),
-                        "..".into(),
-                        "╔ ".into(),
-                    ]
-                } else {
-                    vec![
-                        "║ ".into(),
-                        "║ ".into(),
-                        "║ ".into(),
-                    ]
+                return (Outcome::GaveUp, game.turn());
+            }
+        };
+        let from = game.player();
+        let events = match game.act(decision.action.clone()) {
+            Ok(events) => events,
+            // The solver only picks actions that are allowed, so this is a bug - but it's better
+            // to stop than to panic part way through a run of games
+            Err(e) => {
+                if explain {
+                    println!("The solver tried to {}, but: {}", decision.action, e);
                 }
+                break;
             }
+        };
+
+        if explain {
+            println!();
+            println!("Turn {}, in room {}: {}", game.turn(), from, decision.action);
+            println!("  {}", decision.reason);
+            print_events(&events);
         }
 
-        // position should be of the top-left corner - so we'll expect to go
-        // up and to the left
-        fn set_room(data: &mut [char; SIZE], r: Room, idx: usize, center: bool, row: usize, col: usize) {
-            if center {
-                overwrite(data, top_bar(r, center), row-1, col);
-                overwrite(data, left_side(r, center), row+1, col-3);
-                overwrite(data, right_side(r, center), row+1, col+10);
-            } else {
-                overwrite(data, top_bar(r, center), row-1, col);
-                overwrite(data, left_side(r, center), row+1, col-1);
-                overwrite(data, right_side(r, center), row+1, col+8);
-            }
+        if game.is_over() {
+            return (Outcome::from_events(&events), game.turn());
+        }
+    }
 
-            overwrite(data, bot_bar(r, center), row+4, col);
-
-            // do work on the middle section
-            let middle = if center {
-                vec![
-                    "         ".into(),
-                    "   You   ".into(),
-                    format!("{:^9}", idx),
-                ]
-            } else {
-                vec![
-                    "       ".into(),
-                    format!("{:^7}", idx),
-                    "       ".into(),
-                ]
-            };
+    (Outcome::GaveUp, game.turn())
+}
 
-            overwrite(data, middle, row+1, col+1);
+// Handles 'wumpus solve'
+fn run_solve(args: SolveArgs) -> Result<(), String> {
+    let seed = args.cave.seed();
+
+    if args.games == 1 {
+        let game = args.cave.game_from_seed(seed)?;
+        println!("Seed: {}", seed);
+        let (end, turns) = solve_game(game, true);
+        println!();
+        match end {
+            Outcome::Won => println!("The solver won in {} turns.", turns),
+            _ => println!("The solver lost after {} turns: it {}.", turns, end),
         }
-        
-        // Initializing the thing to display.
-        let mut display = [' '; SIZE];
-        for r in 0 .. HEIGHT {
-            display[idx(r, WIDTH)] = '\n';
+        return Ok(());
+    }
+
+    let mut endings: BTreeMap<Outcome, u32> = BTreeMap::new();
+    let mut winning_turns = 0;
+    for i in 0..args.games {
+        let game = args.cave.game_from_seed(seed.wrapping_add(i as u64))?;
+        let (end, turns) = solve_game(game, false);
+        *endings.entry(end).or_default() += 1;
+        if end == Outcome::Won {
+            winning_turns += turns;
         }
+    }
 
-        let r = self.rooms[room_idx];
+    let percent = |n: u32| 100.0 * n as f64 / args.games as f64;
+    let won = endings.remove(&Outcome::Won).unwrap_or(0);
+    println!("Seeds {} to {}", seed, seed.wrapping_add(args.games as u64 - 1));
+    print!("Won {} of {} games ({:.1}%)", won, args.games, percent(won));
+    if won > 0 {
+        print!(", in {:.1} turns on average", winning_turns as f64 / won as f64);
+    }
+    println!();
 
-        for &(rr, d) in r.iter() {
-            let (row, col) = match d {
-                North => (1, 13),
-                South => (11, 13),
-                East => (6, 25),
-                West => (6, 1),
-            };
+    let mut losses: Vec<_> = endings.into_iter().collect();
+    losses.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+    for (end, n) in losses {
+        println!("  {:>6} ({:>5.1}%) {}", n, percent(n), end);
+    }
+    Ok(())
+}
+
+// Handles 'wumpus simulate'
+fn run_simulate(args: SimulateArgs) -> Result<(), String> {
+    let mut rules = args.rules.rules()?;
+    // The point is to compare cave sizes, so the classic rules are played in generated caves
+    rules.classic_cave = false;
+
+    let (first, last) = args.rooms;
+    if !rules.fit(first as usize) {
+        return Err(format!("there isn't enough room for all of the hazards in {} rooms", first));
+    }
+
+    let seed = args.seed();
+    let columns = ["won", "pit", "eaten", "arrows", "shot self", "gave up"];
+    if args.csv {
+        println!(
+            "rooms,games,won,fell_in_pit,eaten,out_of_arrows,shot_self,gave_up,average_turns,\
+             bat_drops,drops_into_danger"
+        );
+    } else {
+        let last_seed = seed.wrapping_add(args.games as u64 - 1);
+        println!("Policy: {}, seeds {} to {}", args.policy, seed, last_seed);
+        print!("{:>6}", "rooms");
+        for c in columns.iter() {
+            print!(" {:>9}", c);
+        }
+        println!(" {:>7} {:>9} {:>9}", "turns", "bat drops", "in danger");
+    }
+
+    for n_rooms in (first..=last).step_by(args.step as usize) {
+        let tally = simulate(&rules, n_rooms as usize, args.policy, seed, args.games);
+        let counts = Outcome::ALL.map(|o| tally.count(o));
+
+        if args.csv {
+            print!("{},{}", n_rooms, tally.games);
+            for n in counts.iter() {
+                print!(",{}", n);
+            }
+            println!(",{:.2},{},{}", tally.average_turns(), tally.bat_drops, tally.dangerous_drops);
+        } else {
+            let percent = |n: u32, of: u32| 100.0 * n as f64 / of.max(1) as f64;
+            print!("{:>6}", n_rooms);
+            for &n in counts.iter() {
+                print!(" {:>8.1}%", percent(n, tally.games));
+            }
+            println!(
+                " {:>7.1} {:>9} {:>8.1}%",
+                tally.average_turns(),
+                tally.bat_drops,
+                percent(tally.dangerous_drops, tally.bat_drops)
+            );
+        }
+    }
+    Ok(())
+}
 
-            set_room(&mut display, self.rooms[rr], rr, false, row, col);
+// Handles 'wumpus export'
+fn run_export(args: ExportArgs) -> Result<(), String> {
+    let (game, _) = load_or_new(&args.cave, args.load.as_deref())?;
+    let out = game.maze().export(args.format);
+
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, out).map_err(|e| format!("failed to write '{}': {}", path, e))
+        }
+        None => {
+            print!("{}", out);
+            Ok(())
         }
+    }
+}
 
-        set_room(&mut display, r, room_idx, true, 6, 12);
+// Loads the saved game at `load` if there is one, and otherwise starts a new game, which comes
+// with its seed
+fn load_or_new(cave: &CaveArgs, load: Option<&str>) -> Result<(Game, Option<u64>), String> {
+    match load {
+        Some(_) if cave.changes_rules() => {
+            Err("the rules can't be changed when loading a game".into())
+        }
+        Some(_) if cave.n_rooms().is_some() => {
+            Err("the number of rooms can't be given when loading a game".into())
+        }
+        Some(path) => match load_game(path) {
+            Ok(game) => Ok((game, None)),
+            Err(e) => Err(format!("failed to load '{}': {}", path, e)),
+        },
+        None => cave.new_game().map(|(game, seed)| (game, Some(seed))),
+    }
+}
 
-        println!("{}", display.into_iter().collect::<String>());
+// Handles 'wumpus play', which is also what happens without a subcommand
+fn run_play(args: PlayArgs) -> Result<(), String> {
+    let (mut game, seed) = load_or_new(&args.cave, args.load.as_deref())?;
+    if let Some(seed) = seed {
+        println!("Seed: {}", seed);
     }
 
-    fn do_shoot(&mut self, player_room: usize, stdin: &std::io::Stdin) -> bool {
-        let dist = loop {
-            print!("How far do you want to shoot? ");
-            std::io::stdout().flush().unwrap();
-            let mut input = String::new();
+    if args.auto_mark {
+        game.set_auto_mark(true);
+    }
 
-            stdin.read_line(&mut input).unwrap();
+    let renderer = args.style.unwrap_or_else(detect_style).renderer();
+    let recorder = Recorder::new(&game, args.record, args.practice);
+    let (game, _) = play(game, renderer, args.tui, recorder, true)?;
+    finish(&game, true);
+    Ok(())
+}
 
-            input = input.trim().to_lowercase();
+// The full-screen interface needs somewhere to draw
+fn check_terminal() -> Result<(), String> {
+    if std::io::stdout().is_terminal() {
+        Ok(())
+    } else {
+        Err("'--tui' can only be used in a terminal".into())
+    }
+}
 
-            // attempt to parse the distance
-            let dist: i32 = match input.parse() {
-                Ok(d) => d,
-                Err(_) => {
-                    println!("Please enter a number.");
-                    continue;
-                },
-            };
+// Plays a game until it's over or the player quits, in the terminal or full-screen, and gives
+// back the game along with a recording of it. `can_load` says whether the player can switch to a
+// saved game part way through.
+fn play(
+    mut game: Game,
+    renderer: &'static dyn Renderer,
+    tui: bool,
+    mut recorder: Recorder,
+    can_load: bool,
+) -> Result<(Game, Replay), String> {
+    if tui {
+        check_terminal()?;
+        let (game, recorder) = tui::run(game, renderer, recorder)
+            .map_err(|e| format!("terminal error: {}", e))?;
+        return Ok((game, recorder.replay));
+    }
+
+    let stdin = std::io::stdin();
 
-            if dist <= 0 || dist > 5 {
-                println!("Please enter a number between 1 and 5.");
+    let mut next = true;
+    let mut events = game.senses();
+    loop {
+        if next {
+            println!("{}", game.render_room(renderer));
+            next = false;
+        }
+
+        print_events(&events);
+        events.clear();
+
+        let line = prompt(&stdin, "Please pick a direction: ");
+        let (cmd, arg) = split_command(&line);
+
+        let action = match parse_direction(&cmd) {
+            Some(d) => Action::Move(d),
+            None if cmd == "shoot" && arg.is_empty() => read_shot(&game, renderer, &stdin),
+            None if cmd == "shoot" => match parse_shot(arg) {
+                Some(shot) => shot,
+                None => {
+                    println!("Usage: shoot <room> <room> ... (e.g. 'shoot 12 37 5'), or");
+                    println!("shoot <direction> <direction> ... (e.g. 'shoot n e e'). Enter just");
+                    println!("'shoot' to aim one room at a time.");
+                    continue;
+                }
+            },
+            None if cmd == "quit" || cmd == "exit" => break,
+            None if cmd == "mark" || cmd == "unmark" || cmd == "notes" || cmd == "automark" => {
+                if notes_command(&mut game, &cmd, arg) {
+                    next = true;
+                }
                 continue;
-            } else if dist > self.arrows {
-                println!("You don't have enough arrows to shoot that far!");
-                println!("You only have {}.", self.arrows);
+            }
+            None if cmd == "undo" || cmd == "rewind" => {
+                if take_back(&mut game, &mut recorder, &cmd, arg) {
+                    events = game.senses();
+                    next = true;
+                }
+                continue;
+            }
+            None if cmd == "map" => {
+                println!();
+                print!("{}", game.map());
+                continue;
+            }
+            None if cmd == "save" || cmd == "load" => {
+                if arg.is_empty() {
+                    println!("Please give a file name, e.g. '{} wumpus.json'", cmd);
+                } else if cmd == "save" {
+                    match save_game(&game, arg) {
+                        Ok(()) => println!("Saved the game to '{}'", arg),
+                        Err(e) => println!("Failed to save the game: {}", e),
+                    }
+                } else if !can_load {
+                    println!("You can't switch to another game in the middle of this one.");
+                } else {
+                    match load_game(arg) {
+                        Ok(g) => {
+                            game = g;
+                            // A recording can't jump between games, so it starts over
+                            recorder.restart(&game);
+                            events = game.senses();
+                            next = true;
+                            println!("Loaded the game from '{}'", arg);
+                        }
+                        Err(e) => println!("Failed to load '{}': {}", arg, e),
+                    }
+                }
+                continue;
+            }
+            None => {
+                println!("Directions should be left/right/up/down or north/south/east/west");
+                println!("Enter 'shoot' followed by rooms or directions to fire an arrow");
+                println!("Enter 'map' to see a map of the rooms you've explored");
+                println!("Enter 'mark <room> <note>', 'unmark <room>' or 'notes' to keep notes");
+                println!("Enter 'save <file>' or 'load <file>' to save or load the game");
+                if recorder.can_rewind() {
+                    println!("Enter 'undo' or 'rewind <turns>' to take back turns");
+                }
+                println!("Enter 'quit' to quit");
                 continue;
             }
-
-            self.arrows -= dist;
-
-            break dist;
         };
 
-        let mut idx = player_room;
-        for _ in 0 .. dist {
-            self.display_room(idx);
-
-            idx = loop {
-                print!("Pick a direction to continue the shot: ");
-                std::io::stdout().flush().unwrap();
-                let mut input = String::new();
-                stdin.read_line(&mut input).unwrap();
-                input = input.trim().to_lowercase();
-
-                let direction = match input.as_ref() {
-                    "left" | "west" => West,
-                    "right" | "east" => East,
-                    "up" | "north" => North,
-                    "down" | "south" => South,
-                    "quit" | "exit" => exit(0),
-                    _ => {
-                        println!("Directions should be left/right/up/down or north/south/east/west");
-                        println!("Enter 'quit' to quit");
-                        continue;
-                    },
-                };
-
-                let r = self.rooms[idx];
-                if let Some(i) = r.iter().position(|(_,d)| d == &direction) {
-                    break r[i].0;
+        let before = game.clone();
+        match game.act(action.clone()) {
+            Ok(es) => {
+                if let Err(e) = recorder.record(before, action, &es) {
+                    println!("{}", e);
                 }
+                events = es;
+            }
+            Err(ActionError::NoTunnel { .. }) if matches!(action, Action::Move(_)) => {
+                println!("Can't go that way!");
+                continue;
+            }
+            Err(e) => {
+                println!("{}", e);
+                continue;
+            }
+        }
 
-                println!("Can't shoot that way, there's a wall!");
-            };
+        // Bats drop the player into a new room, which gets drawn before anything else that
+        // happens there
+        let carried = events.iter().any(|e| matches!(e, Event::BatsCarriedYou { .. }));
+        if carried {
+            events.retain(|e| {
+                let is_bats = matches!(e, Event::BatsCarriedYou { .. });
+                if is_bats {
+                    println!("{}", e);
+                }
+                !is_bats
+            });
+        }
 
-            if idx == player_room && idx == self.wumpus {
-                println!("You have done the impossible!");
-                println!("You've killed yourself and the Wumpus in one fell swoop!");
-                return true;
-            } else if idx == self.wumpus {
-                println!("You killed the Wumpus!");
-                return true;
-            } else if idx == player_room {
-                println!("You killed... yourself.");
-                return true;
+        if game.is_over() {
+            if carried {
+                println!("{}", game.render_room(renderer));
             }
+            print_events(&events);
+
+            // A practice game can still be carried on from before the end
+            if !recorder.can_rewind() || !offer_rewind(&mut game, &mut recorder, &stdin) {
+                break;
+            }
+            events = game.senses();
         }
 
-        println!("You didn't hit anything...");
+        next = true;
+    }
 
-        if self.arrows == 0 {
-            println!("You ran out of arrows! You lose.");
+    Ok((game, recorder.replay))
+}
+
+// Gives the player the chance to take back the end of a practice game, returning whether they did
+fn offer_rewind(game: &mut Game, recorder: &mut Recorder, stdin: &Stdin) -> bool {
+    loop {
+        let line = prompt(
+            stdin,
+            "Enter 'undo' or 'rewind <turns>' to take that back, or anything else to finish: ",
+        );
+        let (cmd, arg) = split_command(&line);
+        if cmd != "undo" && cmd != "rewind" {
+            return false;
+        } else if take_back(game, recorder, &cmd, arg) {
             return true;
         }
+    }
+}
 
-        // move the wumpus
-        if rand::random::<f32>() < WUMPUS_MOVE_PROB {
-            let new_idx = (rand::random::<f32>() * 3.0) as usize;
-            self.wumpus = self.rooms[self.wumpus][new_idx].0;
-        } 
+// Shows how the game went once the player is done with it, and adds it to the lifetime stats and
+// (if it's `scored`) the high scores. The whole cave is only shown if the game is over, since a
+// game that was quit might have been saved to carry on with later.
+fn finish(game: &Game, scored: bool) {
+    println!("GAME OVER");
+    let result = match Outcome::of(game) {
+        Some(Outcome::Won) => "You killed every wumpus!",
+        Some(Outcome::FellInPit) => "You fell into a bottomless pit.",
+        Some(Outcome::Eaten) => "You were eaten by a wumpus.",
+        Some(Outcome::OutOfArrows) => "You ran out of arrows.",
+        Some(Outcome::ShotYourself) => "You shot yourself.",
+        Some(Outcome::GaveUp) | None => "You gave up.",
+    };
+    println!("{}", result);
+    println!();
+
+    if game.is_over() {
+        // Huge caves make for maps thousands of columns wide, which are no use in a terminal
+        let map = game.full_map();
+        let width = crossterm::terminal::size().map_or(DEFAULT_WIDTH, |(cols, _)| cols as usize);
+        if map.lines().all(|line| line.chars().count() <= width) {
+            print!("{}", map);
+        } else {
+            println!("The cave is too big to show here, but 'wumpus export' can draw all of it.");
+        }
+        println!();
+    }
 
-        if self.wumpus == player_room {
-            println!("You woke up the wumpus and he ate you!");
-            return true;
+    println!("Turns taken:      {}", game.turn());
+    println!("Rooms visited:    {} of {}", game.visited().len(), game.maze().rooms.len());
+    println!("Arrows fired:     {}", game.shots());
+    println!("Carried by bats:  {}", game.bat_carries());
+
+    record_score(game, scored);
+    if game.is_over() {
+        let updated = data_path(STATS_FILE).and_then(|path| {
+            let mut stats = load_data(&path, Stats::load)?;
+            stats.add(game);
+            save_data(&path, |w| stats.save(w))
+        });
+        if let Err(e) = updated {
+            println!("Couldn't save your stats: {}", e);
         }
+    }
+}
 
-        false
+// Where the high scores and stats are kept, along with anything else that should outlive a
+// single game
+fn data_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("wumpus")),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share/wumpus")),
     }
 }
 
-const WUMPUS_MOVE_PROB: f32 = 0.75;
-const STARTING_ARROWS: i32 = 5;
+// The path of one of the files in the data directory
+fn data_path(file: &str) -> Result<PathBuf, String> {
+    data_dir()
+        .map(|dir| dir.join(file))
+        .ok_or_else(|| format!("can't tell where to keep '{}': $HOME isn't set", file))
+}
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("You must provide exactly one argument.");
-        return;
+// Reads one of the files in the data directory, giving the default if it hasn't been written yet
+fn load_data<T, F>(path: &Path, load: F) -> Result<T, String>
+where
+    T: Default,
+    F: FnOnce(BufReader<File>) -> Result<T, SaveError>,
+{
+    match File::open(path) {
+        Ok(file) => load(BufReader::new(file))
+            .map_err(|e| format!("failed to read '{}': {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("failed to read '{}': {}", path.display(), e)),
     }
+}
+
+fn save_data<F>(path: &Path, save: F) -> Result<(), String>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), SaveError>,
+{
+    let write = || -> Result<(), SaveError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        save(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    };
+    write().map_err(|e| format!("failed to write '{}': {}", path.display(), e))
+}
 
-    let n_rooms = match args[1].parse::<i32>() {
-        Ok(n) => n,
-        Err(_) => {
-            println!("You must give a number for the rooms");
+// Tells the player their score if they won, and puts it in the high score table if it's good
+// enough
+fn record_score(game: &Game, scored: bool) {
+    let points = match game.score() {
+        Some(points) => points,
+        None => return,
+    };
+    println!("You scored {} points.", points);
+
+    let score = match game.high_score() {
+        Some(score) if scored => score,
+        Some(_) => {
+            println!("This game doesn't count towards the high scores.");
             return;
-        },
+        }
+        None if game.rewound() => {
+            println!("Games with turns taken back don't go in the high score table.");
+            return;
+        }
+        None => {
+            println!("Only generated caves go in the high score table.");
+            return;
+        }
     };
 
-    if n_rooms < 4 || !(n_rooms % 2 == 0) {
-        println!("The number of rooms must be even and ≥ 4");
-        return;
+    let rules = game.rules().name();
+    let rooms = game.maze().rooms.len();
+    let added = data_path(SCORES_FILE).and_then(|path| {
+        let mut scores = load_data(&path, HighScores::load)?;
+        let rank = scores.add(&rules, rooms, score);
+        if rank.is_some() {
+            save_data(&path, |w| scores.save(w))?;
+        }
+        Ok(rank)
+    });
+
+    match added {
+        Ok(Some(rank)) => println!(
+            "That's number {} in the high score table for the {} rules in {} rooms.",
+            rank + 1,
+            rules,
+            rooms
+        ),
+        Ok(None) => (),
+        Err(e) => println!("Couldn't save the high score: {}", e),
     }
+}
 
-    let n_adds = ((n_rooms - 4) / 2) as u32;
-
-    let mut maze = Maze::generate(n_adds);
+// Handles 'wumpus daily'
+fn run_daily(args: DailyArgs) -> Result<(), String> {
+    let date = Date::today();
+    let path = data_path(DAILY_FILE)?;
+    // A log that can't be read shouldn't stop the game, so it's started again
+    let mut log = load_data(&path, DailyLog::load).unwrap_or_else(|e| {
+        println!("Couldn't read your past daily results, so they'll be replaced: {}", e);
+        DailyLog::default()
+    });
+
+    // Anything that can stop the game from being played has to be checked before the attempt is
+    // recorded below
+    if args.tui {
+        check_terminal()?;
+    }
+    let renderer = args.style.unwrap_or_else(detect_style).renderer();
 
-    let stdin = std::io::stdin();
+    let mut game = daily_game(date);
+    if args.auto_mark {
+        game.set_auto_mark(true);
+    }
 
-    let mut r_idx = 0;
-    let mut next = true;
-    loop {
-        if next {
-            maze.display_room(r_idx);
-            next = false;
+    println!("The daily cave for {}: {} rooms, with the {} rules", date, DAILY_ROOMS, DAILY_RULES);
+    let scored = match log.get(date) {
+        Some(result) => {
+            println!("You've already played today, so this game won't count. You got:");
+            println!();
+            println!("{}", result.share);
+            println!();
+            false
+        }
+        None => {
+            // The attempt counts as soon as it starts, so that quitting doesn't give another go
+            let result = DailyResult {
+                outcome: Outcome::GaveUp,
+                turns: 0,
+                share: share(date, &game, &[]),
+            };
+            log.set(date, result);
+            save_data(&path, |w| log.save(w))?;
+            true
         }
+    };
 
-        if maze.wumpus == r_idx {
-            // if we don't wake it (waking it moves it)
-            if rand::random::<f32>() > WUMPUS_MOVE_PROB {
-                println!("You woke up the wumpus and he ate you!");
-                break;
-            }
+    let recorder = Recorder::new(&game, None, false);
+    let (game, replay) = play(game, renderer, args.tui, recorder, false)?;
+    finish(&game, scored);
 
-            // pick a new room
-            let new_idx = (rand::random::<f32>() * 3.0) as usize;
-            maze.wumpus = maze.rooms[maze.wumpus][new_idx].0;
+    let text = share(date, &game, &replay.turns);
+    if scored {
+        let result = DailyResult {
+            outcome: Outcome::of(&game).unwrap_or(Outcome::GaveUp),
+            turns: game.turn(),
+            share: text.clone(),
+        };
+        log.set(date, result);
+        if let Err(e) = save_data(&path, |w| log.save(w)) {
+            println!("Couldn't save today's result: {}", e);
         }
+    }
 
-        if maze.bats == r_idx {
-            // pick a new room to go into
-            println!("The bats whisk you away!");
-            // this has the effect of do-while
-            while {
-                r_idx = (rand::random::<f32>() * maze.rooms.len() as f32) as usize;
+    println!();
+    println!("{}", text);
+    Ok(())
+}
 
-                r_idx == maze.bats && r_idx == maze.pit
-            } {}
-            next = true;
-            continue;
-        }
+// Handles 'wumpus stats'
+fn run_stats() -> Result<(), String> {
+    let stats = load_data(&data_path(STATS_FILE)?, Stats::load)?;
+    if stats.games == 0 {
+        println!("You haven't finished any games yet.");
+        return Ok(());
+    }
 
-        if maze.pit == r_idx {
-            println!("You fall into a bottomless pit!");
-            break;
-        }
+    let percent = |n: u32, of: u32| 100.0 * n as f64 / of as f64;
+    println!(
+        "Won {} of {} games ({:.1}%), in {:.1} turns on average",
+        stats.wins,
+        stats.games,
+        percent(stats.wins, stats.games),
+        stats.turns as f64 / stats.games as f64
+    );
+    println!("Arrows fired: {}", stats.shots);
+    println!("Carried off by bats: {}", stats.bat_carries);
+    println!();
+
+    println!("Rooms  Games  Won");
+    for (rooms, record) in &stats.sizes {
+        let won = percent(record.wins, record.games);
+        println!("{:>5}  {:>5}  {:>5.1}%", rooms, record.games, won);
+    }
 
-        if maze.rooms[r_idx].iter().any(|(r,_)| r == &maze.wumpus) {
-            println!("You smell something terrible nearby.");
-        }
+    if let Some((death, n)) = stats.most_common_death() {
+        let death = match death {
+            Outcome::FellInPit => "falling into a pit",
+            Outcome::Eaten => "being eaten by a wumpus",
+            Outcome::OutOfArrows => "running out of arrows",
+            _ => "shooting yourself",
+        };
+        println!();
+        let losses = stats.games - stats.wins;
+        println!("Most common way to lose: {} ({} of {} losses)", death, n, losses);
+    }
+    Ok(())
+}
 
-        if maze.rooms[r_idx].iter().any(|(r,_)| r == &maze.bats) {
-            println!("You hear a rustling.");
+// Handles 'wumpus scores'
+fn run_scores(args: ScoresArgs) -> Result<(), String> {
+    let rules = args.rules_name()?;
+    let scores = load_data(&data_path(SCORES_FILE)?, HighScores::load)?;
+
+    let mut tables: Vec<_> = scores
+        .tables()
+        .iter()
+        .filter(|t| rules.as_ref().is_none_or(|r| *r == t.rules))
+        .filter(|t| args.rooms.is_none_or(|n| n as usize == t.rooms))
+        .collect();
+    if tables.is_empty() {
+        println!("There aren't any high scores yet.");
+        return Ok(());
+    }
+    tables.sort_by(|a, b| (&a.rules, a.rooms).cmp(&(&b.rules, b.rooms)));
+
+    for table in tables {
+        println!("The {} rules, in {} rooms:", table.rules, table.rooms);
+        println!("       points  turns  arrows left  explored  seed");
+        for (i, s) in table.scores.iter().enumerate() {
+            let explored = format!("{}/{}", s.rooms_visited, table.rooms);
+            println!(
+                "  {:>2}. {:>6}  {:>5}  {:>11}  {:>8}  {}",
+                i + 1,
+                s.points,
+                s.turns,
+                s.arrows_left,
+                explored,
+                s.seed
+            );
         }
+        println!();
+    }
 
-        if maze.rooms[r_idx].iter().any(|(r,_)| r == &maze.pit) {
-            println!("You feel a cold wind blowing from a nearby cavern.");
-        }
+    println!("Any of these games can be played again with --seed, and the same rules and rooms.");
+    Ok(())
+}
 
-        print!("Please pick a direction: ");
-        std::io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        stdin.read_line(&mut input).unwrap();
-        input = input.trim().to_lowercase();
+fn main() {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        None => run_play(cli.play),
+        Some(Command::Play(args)) => run_play(args),
+        Some(Command::Replay(args)) => run_replay(args),
+        Some(Command::Export(args)) => run_export(args),
+        Some(Command::Solve(args)) => run_solve(args),
+        Some(Command::Simulate(args)) => run_simulate(args),
+        Some(Command::Scores(args)) => run_scores(args),
+        Some(Command::Stats) => run_stats(),
+        Some(Command::Daily(args)) => run_daily(args),
+    };
 
-        let direction = match input.as_ref() {
-            "left" | "west" => West,
-            "right" | "east" => East,
-            "up" | "north" => North,
-            "down" | "south" => South,
-            "shoot" => {
-                // returns true if the game should be over 
-                if maze.do_shoot(r_idx, &stdin) {
-                    break;
-                } else {
-                    next = true;
-                    continue;
-                }
-            },
-            "quit" | "exit" => break,
-            _ => {
-                println!("Directions should be left/right/up/down or north/south/east/west");
-                println!("Enter 'quit' to quit");
-                continue;
-            },
-        };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        exit(1);
+    }
+}
 
-        let r = maze.rooms[r_idx];
-        let i = match r.iter().position(|(_,d)| d == &direction) {
-            Some(i) => i,
-            None => {
-                println!("Can't go that way!");
-                continue;
-            },
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wumpus::Rules;
 
-        next = true;
-        r_idx = r[i].0;
+    // Everything about the game except whether it's been rewound
+    fn state(game: &Game) -> serde_json::Value {
+        let mut json = serde_json::to_value(game).unwrap();
+        json.as_object_mut().unwrap().remove("rewound");
+        json
     }
 
-    println!("GAME OVER");
+    fn take_turn(game: &mut Game, recorder: &mut Recorder, i: usize) {
+        let before = game.clone();
+        let action = Action::Move(game.maze().rooms[game.player()][i % 3].1);
+        let events = game.act(action.clone()).unwrap();
+        recorder.record(before, action, &events).unwrap();
+    }
+
+    #[test]
+    fn rewind_restores_an_earlier_turn() {
+        let mut game = Game::from_seed(10, Rules::preset("easy").unwrap(), 12);
+        let mut recorder = Recorder::new(&game, None, true);
+        take_turn(&mut game, &mut recorder, 0);
+        let after_one = game.clone();
+        take_turn(&mut game, &mut recorder, 1);
+        take_turn(&mut game, &mut recorder, 2);
+
+        let mut rewound = recorder.rewind(2).unwrap();
+        assert!(rewound.rewound());
+        assert_eq!(state(&rewound), state(&after_one));
+        assert_eq!(recorder.replay.turns.len(), 1);
+
+        // The random number generator goes back too, so the game carries on the same way
+        let mut original = after_one;
+        for i in 1..10 {
+            let action = Action::Move(original.maze().rooms[original.player()][i % 3].1);
+            assert_eq!(original.act(action.clone()), rewound.act(action));
+        }
+    }
+
+    #[test]
+    fn rewind_needs_practice_and_turns() {
+        let mut game = Game::from_seed(10, Rules::preset("easy").unwrap(), 12);
+        let mut recorder = Recorder::new(&game, None, false);
+        take_turn(&mut game, &mut recorder, 0);
+        assert!(recorder.rewind(1).is_err());
+
+        let mut recorder = Recorder::new(&game, None, true);
+        assert!(recorder.rewind(1).is_err());
+        take_turn(&mut game, &mut recorder, 1);
+        assert!(recorder.rewind(2).is_err());
+        assert!(recorder.rewind(1).is_ok());
+    }
 }
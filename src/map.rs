@@ -0,0 +1,200 @@
+//! Drawing a map of everything the player has explored so far, or of the whole cave once the
+//! game is over
+
+use std::collections::BTreeMap;
+
+use crate::game::Game;
+use crate::layout;
+
+// The number of rows between each layer of the map
+const LAYER_HEIGHT: usize = 3;
+
+impl Game {
+    /// Draws every room the player has visited, along with all of the tunnels out of them
+    ///
+    /// Visited rooms are shown as `[n]`, with the player's current room as `<n>`. Rooms that have
+    /// been seen through a tunnel but not visited are shown as `?`. Any arrows lying in a room are
+    /// shown after it, like `[n]+2`.
+    pub fn map(&self) -> String {
+        let rooms = &self.maze().rooms;
+
+        // Assign each room on the map a local index, starting with the visited ones
+        let mut local = BTreeMap::new();
+        let mut shown = Vec::new();
+        // Games saved before rooms were tracked won't have the current room marked as visited
+        let mut visited = self.visited().clone();
+        visited.insert(self.player());
+        for &r in &visited {
+            local.insert(r, shown.len());
+            shown.push(r);
+        }
+
+        let mut edges = Vec::new();
+        for &r in &visited {
+            for &(next, _) in rooms[r].iter() {
+                let n = *local.entry(next).or_insert_with(|| {
+                    shown.push(next);
+                    shown.len() - 1
+                });
+
+                // Tunnels between two visited rooms are seen from both ends
+                if !visited.contains(&next) || r < next {
+                    edges.push((local[&r], n));
+                }
+            }
+        }
+
+        let label = |room: usize| {
+            let mut label = if room == self.player() {
+                format!("<{}>", room)
+            } else if visited.contains(&room) {
+                format!("[{}]", room)
+            } else {
+                "?".to_string()
+            };
+
+            if let Some(count) = self.maze().dropped.get(&room) {
+                label.push_str(&format!("+{}", count));
+            }
+            label
+        };
+        let labels: Vec<String> = shown.iter().map(|&r| label(r)).collect();
+
+        let mut out = draw(&labels, &edges, local[&self.player()]);
+        out.push_str("<n> you are here   [n] visited   ? unexplored");
+        if !self.maze().dropped.is_empty() {
+            out.push_str("   +n arrows on the floor");
+        }
+        out.push('\n');
+        out
+    }
+
+    /// Draws the whole cave, with every hazard - for once the game is over
+    ///
+    /// Rooms the player visited are shown as `[n]` and the rest as `(n)`, with the room they
+    /// finished in as `<n>`. Bats, pits and wumpuses are shown after the room as `B`, `P` and
+    /// `W`.
+    pub fn full_map(&self) -> String {
+        let maze = self.maze();
+        let mut edges = Vec::new();
+        for (r, room) in maze.rooms.iter().enumerate() {
+            edges.extend(room.iter().filter(|&&(next, _)| r < next).map(|&(next, _)| (r, next)));
+        }
+
+        let label = |room: usize| {
+            let mut label = if room == self.player() {
+                format!("<{}>", room)
+            } else if self.visited().contains(&room) {
+                format!("[{}]", room)
+            } else {
+                format!("({})", room)
+            };
+
+            let hazards = [(&maze.bats, 'B'), (&maze.pits, 'P'), (&maze.wumpuses, 'W')];
+            label.extend(hazards.iter().filter(|(rooms, _)| rooms.contains(&room)).map(|h| h.1));
+            if let Some(count) = maze.dropped.get(&room) {
+                label.push_str(&format!("+{}", count));
+            }
+            label
+        };
+        let labels: Vec<String> = (0..maze.rooms.len()).map(label).collect();
+
+        let mut out = draw(&labels, &edges, self.player());
+        out.push_str("<n> where you ended up   [n] visited   (n) never visited\n");
+        out.push_str("B bats   P bottomless pit   W wumpus");
+        if !maze.dropped.is_empty() {
+            out.push_str("   +n arrows on the floor");
+        }
+        out.push('\n');
+        out
+    }
+}
+
+// Lays out and draws the rooms with the given labels, joined by `edges` (which index into
+// `labels`), starting from the room at `root`
+fn draw(labels: &[String], edges: &[(usize, usize)], root: usize) -> String {
+    let coords = layout::layered(labels.len(), edges, root);
+
+    let max_label = labels.iter().map(|l| l.chars().count()).max().unwrap_or(1);
+    let spacing = max_label + 3;
+    let min_x = coords.iter().map(|c| c.0).fold(f32::INFINITY, f32::min);
+    let max_x = coords.iter().map(|c| c.0).fold(f32::NEG_INFINITY, f32::max);
+    let max_y = coords.iter().map(|c| c.1).fold(0.0, f32::max);
+
+    // Every layer is centered on zero, so positions can be half-way between whole numbers.
+    // Doubling them first keeps everything on the grid.
+    let half = spacing / 2;
+    let center = |i: usize| {
+        let (x, y) = coords[i];
+        let col = max_label / 2 + ((x - min_x) * 2.0) as usize * half;
+        (col, y as usize * LAYER_HEIGHT)
+    };
+
+    let width = max_label + ((max_x - min_x) * 2.0) as usize * half + 1;
+    let height = max_y as usize * LAYER_HEIGHT + 1;
+    let mut grid = vec![vec![' '; width]; height];
+
+    for &(a, b) in edges {
+        draw_line(&mut grid, center(a), center(b));
+    }
+
+    for (i, l) in labels.iter().enumerate() {
+        let (col, row) = center(i);
+        let start = col.saturating_sub(l.chars().count() / 2);
+        for (j, c) in l.chars().enumerate() {
+            if let Some(cell) = grid[row].get_mut(start + j) {
+                *cell = c;
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for row in grid {
+        let line: String = row.into_iter().collect();
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+// Draws a straight line between the two points (given as `(column, row)`), not including the
+// endpoints themselves
+fn draw_line(grid: &mut [Vec<char>], from: (usize, usize), to: (usize, usize)) {
+    let (mut x, mut y) = (from.0 as isize, from.1 as isize);
+    let (x1, y1) = (to.0 as isize, to.1 as isize);
+
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let sx = if x < x1 { 1 } else { -1 };
+    let sy = if y < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+
+    // Bresenham's algorithm, choosing the character for each cell by the step taken to reach it
+    while (x, y) != (x1, y1) {
+        let (mut step_x, mut step_y) = (false, false);
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+            step_x = true;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+            step_y = true;
+        }
+
+        if (x, y) == (x1, y1) {
+            break;
+        }
+
+        let c = match (step_x, step_y) {
+            (true, false) => '-',
+            (false, true) => '|',
+            _ if sx == sy => '\\',
+            _ => '/',
+        };
+
+        grid[y as usize][x as usize] = c;
+    }
+}
@@ -0,0 +1,384 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::rules::Rules;
+use crate::STARTING_ARROWS;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    NorthSouth,
+    EastWest,
+}
+
+pub type Room = [(usize, Direction); 3];
+
+/// The number of each kind of hazard to place in a generated maze
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hazards {
+    pub bats: usize,
+    pub pits: usize,
+    pub wumpuses: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Maze {
+    pub rooms: Vec<Room>,
+    pub bats: Vec<usize>,
+    pub pits: Vec<usize>,
+    pub wumpuses: Vec<usize>,
+
+    pub arrows: i32,
+    /// Arrows that missed and are lying where they landed, as the number in each room. These are
+    /// only ever left behind when the rules allow arrows to be recovered.
+    #[serde(default)]
+    pub dropped: BTreeMap<usize, i32>,
+    /// The room the player starts in. Generated caves always start in room 0.
+    #[serde(default)]
+    pub start: usize,
+}
+
+use Direction::{East, North, South, West};
+use Orientation::{EastWest, NorthSouth};
+
+impl Direction {
+    // This takes `self` as a receiver instead of `&self` because we
+    // implemented copy.
+    pub fn opposite(self) -> Direction {
+        match self {
+            North => South,
+            South => North,
+            East => West,
+            West => East,
+        }
+    }
+
+    // Finds a direction that isn't present in this list
+    /*
+    fn not_present(ls: &[Self]) -> Option<Direction> {
+        let mut present = [false; 4];
+
+        for d in ls.iter() {
+            match d {
+                Self::North => present[0] = true,
+                Self::South => present[1] = true,
+                Self::East => present[2] = true,
+                Self::West => present[3] = true,
+            }
+        }
+
+        let from_int = |i| match i {
+            0 => Self::North,
+            1 => Self::South,
+            2 => Self::East,
+            3 => Self::West,
+            _ => unreachable!(),
+        };
+
+        present
+            .into_iter()
+            .enumerate()
+            .filter(|(_, &p)| !p)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>()
+            .pop()
+            .map(from_int)
+    }
+    */
+
+    // Gives the orientation
+    pub fn orientation(&self) -> Orientation {
+        match self {
+            North => NorthSouth,
+            South => NorthSouth,
+            East => EastWest,
+            West => EastWest,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            North => "north",
+            South => "south",
+            East => "east",
+            West => "west",
+        };
+
+        f.write_str(s)
+    }
+}
+
+impl Orientation {
+    pub fn major(&self) -> Direction {
+        match self {
+            NorthSouth => North,
+            EastWest => East,
+        }
+    }
+
+    pub fn minor(&self) -> Direction {
+        match self {
+            NorthSouth => South,
+            EastWest => West,
+        }
+    }
+}
+
+impl Maze {
+    pub fn quad() -> Self {
+        let rooms = vec![
+            [(1, West), (2, North), (3, East)],
+            [(0, East), (2, West), (3, North)],
+            [(0, South), (1, East), (3, West)],
+            [(0, West), (1, South), (2, East)],
+        ];
+
+        Self {
+            rooms,
+            bats: Vec::new(),
+            pits: Vec::new(),
+            wumpuses: Vec::new(),
+            arrows: STARTING_ARROWS,
+            dropped: BTreeMap::new(),
+            start: 0,
+        }
+    }
+
+    // Expands the maze to include more rooms at the given index
+    pub fn expand(&mut self, idx: usize, rand_bool: bool) {
+        fn link_idx(idx: usize, r: Room) -> usize {
+            r.iter().position(|(i,_)| i == &idx).unwrap()
+        }
+
+        // It may be helpful to remember:
+        //   self.rooms[idx] = r0
+        //   r0 = [(r0[0].0, r0[0].1), (r0[1].0, r0[1].1), (r0[2].0, r0[2].1)]
+        // Generally, we're taking the connections to r0 and redirecting them
+        // to other nodes.
+        let r0 = self.rooms[idx];
+
+        // The indexes of the tunnel to r0 in the rooms it links to
+        let r0_from_others = [
+            link_idx(idx, self.rooms[r0[0].0]),
+            link_idx(idx, self.rooms[r0[1].0]),
+            link_idx(idx, self.rooms[r0[2].0]),
+        ];
+
+        // We're creating two new rooms: r1 and r2.
+        let r1_idx = self.rooms.len();
+        let r2_idx = r1_idx + 1;
+
+        // indexes of the major/minor directions
+        let (fst_maj, fst_min, snd) = {
+            // directions
+            let ds = r0.iter().map(|(_,d)| d).collect::<Vec<_>>();
+            
+            // check against the other two
+            let o = ds[0].orientation();
+
+            if ds[1].orientation() == o {
+                if o.major() == *ds[0] {
+                    (0, 1, 2)
+                } else {
+                    (1, 0, 2)
+                }
+            } else if ds[2].orientation() == o {
+                if o.major() == *ds[0] {
+                    (0, 2, 1)
+                } else {
+                    (2, 0, 1)
+                }
+            } else {
+                if ds[1].orientation().major() == *ds[1] {
+                    (1, 2, 0)
+                } else {
+                    (2, 1, 0)
+                }
+            }
+        };
+
+        let fst_or = r0[fst_maj].1.orientation();
+        let snd_d = r0[snd].1;
+
+        let r0_new = [
+            (r0[fst_maj].0, fst_or.major()),
+            (r1_idx, fst_or.minor()),
+            (r2_idx, snd_d),
+        ];
+        
+        // we don't need to set this existing room because it's already there.
+
+        let r2_r1_d = if rand_bool {
+            fst_or.major()
+        } else {
+            snd_d
+        };
+
+        let r1 = [
+            (idx, fst_or.major()),
+            (r0[snd].0, snd_d),
+            (r2_idx, r2_r1_d.opposite()),
+        ];
+
+        self.rooms[r0[snd].0][r0_from_others[snd]] = (r1_idx, snd_d.opposite());
+
+        let r2 = [
+            (r1_idx, r2_r1_d), // This just continues from the last of r1. Can be chosen
+            (r0[fst_min].0, fst_or.minor()),
+            (idx, snd_d.opposite()),
+        ];
+
+        self.rooms[r0[fst_min].0][r0_from_others[fst_min]] = (r2_idx, fst_or.major());
+
+        // set all of the rooms
+        self.rooms[idx] = r0_new;
+        self.rooms.push(r1);
+        self.rooms.push(r2);
+    }
+
+    // The total number will be 4 + (2 * adds), with hazards and arrows as given by the rules
+    //
+    // All of the randomness comes from `rng`, so the same generator state will always give the
+    // same maze.
+    //
+    // Panics if the hazards don't fit in the maze; `Rules::fit` can be used to check that
+    // beforehand.
+    pub fn generate<R: Rng>(adds: u32, rules: &Rules, rng: &mut R) -> Self {
+        let mut maze = Self::quad();
+        maze.arrows = rules.starting_arrows;
+
+        for _ in 0 .. adds {
+            let idx = (rng.gen::<f32>() * maze.rooms.len() as f32) as usize;
+            maze.expand(idx, rng.gen());
+        }
+
+        assert!(rules.fit(maze.rooms.len()), "too many hazards for the maze");
+
+        // Hazards are never placed in the starting room, and (unless the rules say otherwise)
+        // only placed next to it once there's nowhere else left. Bats and pits don't share rooms
+        // unless they're allowed to, but the wumpuses can end up anywhere.
+        let (near, far): (Vec<_>, Vec<_>) = if rules.placement.near_start {
+            (Vec::new(), (1 .. maze.rooms.len()).collect())
+        } else {
+            (1 .. maze.rooms.len()).partition(|i| maze.rooms[0].iter().any(|(r,_)| r == i))
+        };
+
+        let hazards = &rules.hazards;
+        let (mut near_static, mut far_static) = (near.clone(), far.clone());
+        maze.bats = (0 .. hazards.bats)
+            .map(|_| take_room(rng, &mut far_static, &mut near_static))
+            .collect();
+        if rules.placement.shared {
+            near_static = near.clone();
+            far_static = far.clone();
+        }
+        maze.pits = (0 .. hazards.pits)
+            .map(|_| take_room(rng, &mut far_static, &mut near_static))
+            .collect();
+
+        let (mut near, mut far) = (near, far);
+        maze.wumpuses = (0 .. hazards.wumpuses)
+            .map(|_| take_room(rng, &mut far, &mut near))
+            .collect();
+
+        maze
+    }
+
+    /// Creates the cave from Gregory Yob's original 1973 game: a dodecahedron, with every hazard
+    /// in a separate room. With `Rules::classic`, that's two rooms of bats, two pits, and the
+    /// wumpus.
+    ///
+    /// Yob numbered the rooms from 1 to 20, so his room `n` is room `n - 1` here.
+    ///
+    /// Panics if the hazards don't fit in the cave; `Rules::fit` can be used to check that
+    /// beforehand.
+    pub fn classic<R: Rng>(rules: &Rules, rng: &mut R) -> Self {
+        assert!(rules.fit(20), "too many hazards for the classic cave");
+
+        let rooms = vec![
+            [(1, North), (4, South), (7, East)],
+            [(0, South), (2, North), (9, East)],
+            [(1, South), (3, North), (11, East)],
+            [(2, South), (4, North), (13, East)],
+            [(0, North), (3, South), (5, East)],
+            [(4, West), (6, North), (14, South)],
+            [(5, South), (7, North), (16, East)],
+            [(0, West), (6, South), (8, North)],
+            [(7, South), (9, North), (17, East)],
+            [(1, West), (8, South), (10, North)],
+            [(9, South), (11, North), (18, East)],
+            [(2, West), (10, South), (12, North)],
+            [(11, South), (13, North), (19, East)],
+            [(3, West), (12, South), (14, North)],
+            [(5, North), (13, South), (15, East)],
+            [(14, West), (16, North), (19, South)],
+            [(6, West), (15, South), (17, North)],
+            [(8, West), (16, South), (18, North)],
+            [(10, West), (17, South), (19, North)],
+            [(12, West), (15, North), (18, South)],
+        ];
+
+        // The player always starts in room 0, so everything else goes in one of the other 19
+        let mut taken = vec![0];
+        let mut place = |rng: &mut R| loop {
+            let i = 1 + (rng.gen::<f32>() * 19.0) as usize;
+            if !taken.contains(&i) {
+                taken.push(i);
+                break i;
+            }
+        };
+
+        let hazards = &rules.hazards;
+        let bats = (0 .. hazards.bats).map(|_| place(rng)).collect();
+        let pits = (0 .. hazards.pits).map(|_| place(rng)).collect();
+        let wumpuses = (0 .. hazards.wumpuses).map(|_| place(rng)).collect();
+
+        Self {
+            rooms,
+            bats,
+            pits,
+            wumpuses,
+            arrows: rules.starting_arrows,
+            dropped: BTreeMap::new(),
+            start: 0,
+        }
+    }
+}
+
+impl Default for Hazards {
+    fn default() -> Self {
+        Self {
+            bats: 1,
+            pits: 1,
+            wumpuses: 1,
+        }
+    }
+}
+
+impl Hazards {
+    /// Returns whether there's room for all of these hazards in a maze with `n_rooms` rooms
+    pub fn fit(&self, n_rooms: usize) -> bool {
+        // The starting room is always left empty
+        let available = n_rooms.saturating_sub(1);
+        self.bats + self.pits <= available && self.wumpuses <= available
+    }
+}
+
+// Removes and returns a random room from `far`, or from `near` if `far` is empty
+fn take_room<R: Rng>(rng: &mut R, far: &mut Vec<usize>, near: &mut Vec<usize>) -> usize {
+    let from = if far.is_empty() { near } else { far };
+    let i = (rng.gen::<f32>() * from.len() as f32) as usize;
+    from.swap_remove(i)
+}
@@ -0,0 +1,199 @@
+//! Markers that the player (or the automatic deduction) can put on rooms
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::deduce::{Hazard, Status};
+use crate::display::Annotation;
+use crate::game::Game;
+use crate::render::{Highlight, Renderer, Unicode};
+
+/// Something noted about a room
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Marker {
+    Safe,
+    /// The hazard is in the room
+    Hazard(Hazard),
+    /// The hazard might be in the room
+    Maybe(Hazard),
+    /// Any other note the player wants to keep
+    Note(String),
+}
+
+impl Marker {
+    /// A short form of the marker, to fit inside a room when it's drawn
+    pub fn short(&self) -> &str {
+        match self {
+            Marker::Safe => "ok",
+            Marker::Hazard(Hazard::Pit) => "P",
+            Marker::Hazard(Hazard::Bats) => "B",
+            Marker::Hazard(Hazard::Wumpus) => "W",
+            Marker::Maybe(Hazard::Pit) => "P?",
+            Marker::Maybe(Hazard::Bats) => "B?",
+            Marker::Maybe(Hazard::Wumpus) => "W?",
+            Marker::Note(_) => "*",
+        }
+    }
+}
+
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hazard = |h| match h {
+            Hazard::Pit => "pit",
+            Hazard::Bats => "bats",
+            Hazard::Wumpus => "wumpus",
+        };
+
+        match self {
+            Marker::Safe => write!(f, "safe"),
+            Marker::Hazard(h) => write!(f, "{}", hazard(*h)),
+            Marker::Maybe(h) => write!(f, "{}?", hazard(*h)),
+            Marker::Note(s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
+impl FromStr for Marker {
+    type Err = std::convert::Infallible;
+
+    /// Parses the long form of a marker, as given by `Display`. Anything that isn't recognized is
+    /// kept as a note.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, maybe) = match s.strip_suffix('?') {
+            Some(name) => (name, true),
+            None => (s, false),
+        };
+
+        let hazard = match name.to_lowercase().as_str() {
+            "safe" | "ok" if !maybe => return Ok(Marker::Safe),
+            "pit" | "p" => Hazard::Pit,
+            "bats" | "bat" | "b" => Hazard::Bats,
+            "wumpus" | "w" => Hazard::Wumpus,
+            _ => return Ok(Marker::Note(s.to_string())),
+        };
+
+        Ok(if maybe {
+            Marker::Maybe(hazard)
+        } else {
+            Marker::Hazard(hazard)
+        })
+    }
+}
+
+impl Game {
+    /// Adds a marker to the room, if it isn't already there
+    pub fn mark(&mut self, room: usize, marker: Marker) {
+        let markers = self.marks.entry(room).or_default();
+        if !markers.contains(&marker) {
+            markers.push(marker);
+        }
+    }
+
+    /// Removes all of the player's markers from the room
+    pub fn unmark(&mut self, room: usize) {
+        self.marks.remove(&room);
+    }
+
+    /// Turns automatic marking on or off. When it's on, rooms that the player hasn't marked
+    /// themselves are marked from what can be deduced from the warnings so far.
+    pub fn set_auto_mark(&mut self, on: bool) {
+        self.auto_mark = on;
+    }
+
+    pub fn auto_mark(&self) -> bool {
+        self.auto_mark
+    }
+
+    /// Gives the markers that the player put on the room
+    pub fn player_markers(&self, room: usize) -> &[Marker] {
+        self.marks.get(&room).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Gives the markers that automatic marking would put on the room. This is available even if
+    /// automatic marking is turned off.
+    pub fn deduced_markers(&self, room: usize) -> Vec<Marker> {
+        let rooms = &self.maze().rooms;
+        let knowledge = self.knowledge();
+
+        if knowledge.is_safe(rooms, room) {
+            return vec![Marker::Safe];
+        }
+
+        let mut markers = Vec::new();
+        for &h in Hazard::ALL.iter() {
+            match knowledge.status(rooms, room, h) {
+                Status::Present => markers.push(Marker::Hazard(h)),
+                Status::Suspected => markers.push(Marker::Maybe(h)),
+                Status::Absent | Status::Unknown => (),
+            }
+        }
+        markers
+    }
+
+    /// Gives the markers to show for the room: the player's own, if there are any, or otherwise
+    /// the deduced ones if automatic marking is on.
+    pub fn markers(&self, room: usize) -> Vec<Marker> {
+        let own = self.player_markers(room);
+        if !own.is_empty() || !self.auto_mark {
+            own.to_vec()
+        } else {
+            self.deduced_markers(room)
+        }
+    }
+
+    /// Gives every room with markers to show, along with those markers
+    pub fn notes(&self) -> Vec<(usize, Vec<Marker>)> {
+        (0..self.maze().rooms.len())
+            .map(|r| (r, self.markers(r)))
+            .filter(|(_, m)| !m.is_empty())
+            .collect()
+    }
+
+    /// Draws the room the player is in, like `Maze::display_room`, with each neighbouring room's
+    /// markers shown inside it
+    pub fn display_room(&self) -> String {
+        self.render_room(&Unicode)
+    }
+
+    /// Like `display_room`, but with the given renderer. The player's room is highlighted, as
+    /// are any rooms with markers.
+    pub fn render_room(&self, renderer: &dyn Renderer) -> String {
+        self.maze().render_room(self.player(), renderer, |r| self.annotate(r))
+    }
+
+    /// Gives the annotation for a room that shows its markers, highlighting the player's room
+    pub fn annotate(&self, room: usize) -> Annotation {
+        if room == self.player() {
+            return Annotation {
+                highlight: Highlight::Player,
+                ..Annotation::default()
+            };
+        }
+
+        let markers = self.markers(room);
+        let spaced = markers.iter().map(Marker::short).collect::<Vec<_>>().join(" ");
+
+        // There isn't much space, so only use spaces if it all fits
+        let label = if spaced.chars().count() <= 7 {
+            spaced
+        } else {
+            markers.iter().map(Marker::short).collect()
+        };
+
+        let is_hazard = |m: &Marker| matches!(m, Marker::Hazard(_) | Marker::Maybe(_));
+        let label_highlight = if markers.iter().any(is_hazard) {
+            Highlight::Hazard
+        } else if markers.contains(&Marker::Safe) {
+            Highlight::Safe
+        } else {
+            Highlight::None
+        };
+
+        Annotation {
+            label,
+            label_highlight,
+            ..Annotation::default()
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! The different ways that rooms can be drawn in a terminal
+//!
+//! Drawing is always done on a grid of characters, using the Unicode box-drawing characters for
+//! walls. A `Renderer` decides what each wall character actually becomes, and how highlighted
+//! parts of the grid are shown.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The reasons that part of a drawing might stand out
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Highlight {
+    None,
+    /// The room the player is in
+    Player,
+    /// A room that's been marked as (possibly) having a hazard
+    Hazard,
+    /// A room that's been marked as safe
+    Safe,
+    /// A room that an arrow is passing through
+    Arrow,
+}
+
+pub trait Renderer {
+    /// Gives the character to draw in place of one of the box-drawing characters used for walls:
+    /// `'╔'`, `'╗'`, `'╚'`, `'╝'`, `'═'` or `'║'`
+    fn wall(&self, c: char) -> char;
+
+    /// Returns the text with the highlight applied. The text never includes a newline.
+    fn paint(&self, text: &str, highlight: Highlight) -> String;
+}
+
+/// Plain 7-bit ASCII, for terminals (and logs) that can't handle anything else
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Ascii;
+
+/// Unicode double-line box drawing, without any colour
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Unicode;
+
+/// Like `Unicode`, but with ANSI colours for highlights
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Color;
+
+impl Renderer for Ascii {
+    fn wall(&self, c: char) -> char {
+        match c {
+            '═' => '-',
+            '║' => '|',
+            '╔' | '╗' | '╚' | '╝' => '+',
+            c => c,
+        }
+    }
+
+    fn paint(&self, text: &str, _: Highlight) -> String {
+        text.into()
+    }
+}
+
+impl Renderer for Unicode {
+    fn wall(&self, c: char) -> char {
+        c
+    }
+
+    fn paint(&self, text: &str, _: Highlight) -> String {
+        text.into()
+    }
+}
+
+impl Renderer for Color {
+    fn wall(&self, c: char) -> char {
+        c
+    }
+
+    fn paint(&self, text: &str, highlight: Highlight) -> String {
+        let code = match highlight {
+            Highlight::None => return text.into(),
+            Highlight::Player => "1;36",
+            Highlight::Hazard => "1;31",
+            Highlight::Safe => "32",
+            Highlight::Arrow => "1;33",
+        };
+
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+}
+
+/// The available renderers, for choosing between them by name
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Style {
+    Ascii,
+    Unicode,
+    Color,
+}
+
+impl Style {
+    pub fn renderer(self) -> &'static dyn Renderer {
+        match self {
+            Style::Ascii => &Ascii,
+            Style::Unicode => &Unicode,
+            Style::Color => &Color,
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Style::Ascii => "ascii",
+            Style::Unicode => "unicode",
+            Style::Color => "color",
+        };
+
+        f.write_str(s)
+    }
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "ascii" => Ok(Style::Ascii),
+            "unicode" => Ok(Style::Unicode),
+            "color" | "colour" => Ok(Style::Color),
+            _ => Err(format!("unknown style '{}', expected ascii, unicode or color", s)),
+        }
+    }
+}
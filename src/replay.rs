@@ -0,0 +1,182 @@
+//! Recording games so that they can be played back later
+//!
+//! Because the engine is deterministic, a replay only needs the starting state of the game and
+//! the actions the player took. The events produced by each action are stored as well, so that
+//! playback can detect when it no longer matches what originally happened.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Write};
+
+use crate::game::{Action, ActionError, Event, Game};
+use crate::save::{check_game, read_versioned, write_versioned, SaveError, SAVE_VERSION};
+
+/// A full record of a game
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    /// The game before the first action was taken
+    pub start: Game,
+    pub turns: Vec<Turn>,
+}
+
+/// A single action, along with everything that happened because of it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Turn {
+    pub action: Action,
+    pub events: Vec<Event>,
+}
+
+/// Steps through a `Replay`, re-running each action on a copy of the starting game
+pub struct Playback<'a> {
+    game: Game,
+    turns: std::slice::Iter<'a, Turn>,
+    done: usize,
+}
+
+/// The ways that playing back a replay can fail
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaybackError {
+    /// The engine rejected the action that was recorded for the given turn
+    Rejected { turn: usize, error: ActionError },
+    /// The action for the given turn produced different events than were recorded
+    Desync { turn: usize, actual: Vec<Event> },
+}
+
+impl fmt::Display for PlaybackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlaybackError::Rejected { turn, error } => {
+                write!(f, "the action for turn {} was rejected: {}", turn, error)
+            }
+            PlaybackError::Desync { turn, .. } => {
+                write!(f, "turn {} didn't play out the same way as when it was recorded", turn)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlaybackError {}
+
+impl Replay {
+    /// Starts a new, empty recording from the current state of the game
+    pub fn new(start: &Game) -> Self {
+        Self {
+            start: start.clone(),
+            turns: Vec::new(),
+        }
+    }
+
+    /// Adds a single turn to the end of the recording
+    pub fn record(&mut self, action: Action, events: &[Event]) {
+        self.turns.push(Turn {
+            action,
+            events: events.to_vec(),
+        });
+    }
+
+    pub fn save<W: Write>(&self, writer: W) -> Result<(), SaveError> {
+        write_versioned(writer, SAVE_VERSION, self)
+    }
+
+    pub fn load<R: Read>(reader: R) -> Result<Replay, SaveError> {
+        let replay: Replay = read_versioned(reader, SAVE_VERSION)?;
+        check_game(&replay.start)?;
+        Ok(replay)
+    }
+
+    pub fn playback(&self) -> Playback<'_> {
+        Playback {
+            game: self.start.clone(),
+            turns: self.turns.iter(),
+            done: 0,
+        }
+    }
+}
+
+impl<'a> Playback<'a> {
+    /// The state of the game after the turns that have been played back so far
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// The number of turns that have been played back so far
+    pub fn turns_done(&self) -> usize {
+        self.done
+    }
+
+    /// Plays back the next turn, returning `None` once there are no more left
+    pub fn step(&mut self) -> Option<Result<&'a Turn, PlaybackError>> {
+        let turn = self.turns.next()?;
+        self.done += 1;
+
+        let result = match self.game.act(turn.action.clone()) {
+            Err(error) => Err(PlaybackError::Rejected {
+                turn: self.done,
+                error,
+            }),
+            Ok(actual) if actual != turn.events => Err(PlaybackError::Desync {
+                turn: self.done,
+                actual,
+            }),
+            Ok(_) => Ok(turn),
+        };
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Rules;
+
+    fn recorded(seed: u64) -> Replay {
+        let mut game = Game::from_seed(10, Rules::preset("hard").unwrap(), seed);
+        let mut replay = Replay::new(&game);
+        for i in 0..30 {
+            let action = Action::Move(game.maze().rooms[game.player()][i % 3].1);
+            match game.act(action.clone()) {
+                Ok(events) => replay.record(action, &events),
+                Err(_) => break,
+            }
+        }
+        replay
+    }
+
+    // Plays back every turn, giving the number played
+    fn play_all(replay: &Replay) -> Result<usize, PlaybackError> {
+        let mut playback = replay.playback();
+        while let Some(turn) = playback.step() {
+            turn?;
+        }
+        Ok(playback.turns_done())
+    }
+
+    #[test]
+    fn playback_matches_the_recording() {
+        let replay = recorded(8);
+        assert!(replay.turns.len() > 3);
+        assert_eq!(play_all(&replay), Ok(replay.turns.len()));
+
+        // The replay survives being written out, too
+        let mut file = Vec::new();
+        replay.save(&mut file).unwrap();
+        let loaded = Replay::load(&file[..]).unwrap();
+        assert_eq!(loaded.turns, replay.turns);
+        assert_eq!(play_all(&loaded), Ok(replay.turns.len()));
+    }
+
+    #[test]
+    fn changed_events_are_a_desync() {
+        let mut replay = recorded(8);
+        replay.turns[2].events.push(Event::HearBats);
+
+        let mut playback = replay.playback();
+        assert!(playback.step().unwrap().is_ok());
+        assert!(playback.step().unwrap().is_ok());
+        match playback.step().unwrap() {
+            Err(PlaybackError::Desync { turn: 3, .. }) => (),
+            other => panic!("expected a desync on turn 3, got {:?}", other),
+        }
+    }
+}
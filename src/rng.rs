@@ -0,0 +1,50 @@
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// The random number generator used for everything in a game
+///
+/// This is a SplitMix64 generator: it isn't cryptographically secure, but its entire state is a
+/// single `u64`. That makes it trivial to seed from the command line and to store alongside a
+/// game, so that the same seed always produces the same game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    /// Creates a new generator, seeded from the system's source of randomness
+    pub fn from_entropy() -> Self {
+        Self::from_seed(rand::random::<u64>())
+    }
+
+    /// Returns the current internal state. Passing this to `from_seed` gives a generator that
+    /// will produce the same values as this one from here onwards.
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl SeedableRng<u64> for GameRng {
+    fn reseed(&mut self, seed: u64) {
+        self.state = seed;
+    }
+
+    fn from_seed(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
@@ -0,0 +1,283 @@
+//! The parts of the game's rules that can differ between games
+//!
+//! Rules can be written as TOML, with any field that's left out taking its value from the
+//! `normal` rules:
+//!
+//! ```toml
+//! starting_arrows = 3
+//! arrow_cost = "per-shot"
+//! wumpus = "hunter"
+//!
+//! [hazards]
+//! pits = 3
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::behaviour::Profile;
+use crate::maze::Hazards;
+use crate::{STARTING_ARROWS, WUMPUS_MOVE_PROB};
+
+/// Everything about how a game is played that can be changed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    pub arrow_cost: ArrowCost,
+    /// The number of each hazard in the cave
+    pub hazards: Hazards,
+    /// Where hazards may be placed
+    pub placement: Placement,
+    /// Whether arrows that miss can be picked up again from the room they land in
+    pub recover_arrows: bool,
+    /// How the wumpuses move around
+    pub wumpus: Profile,
+    /// The chance that a woken wumpus moves, rather than staying where it is
+    pub wumpus_move_prob: f32,
+    pub starting_arrows: i32,
+    /// The shortest and longest distances an arrow can be shot
+    pub min_shot: usize,
+    pub max_shot: usize,
+    /// Whether bats can drop the player into a room with a hazard. If not, they only ever drop
+    /// the player somewhere empty.
+    pub bats_into_hazards: bool,
+    /// Play in the dodecahedron from the original game, instead of a generated cave
+    pub classic_cave: bool,
+}
+
+/// How many arrows a single shot uses up
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArrowCost {
+    /// One arrow for every room the shot travels through
+    #[serde(alias = "per-room")]
+    PerRoom,
+    /// One arrow per shot, no matter how far it goes. This is the rule from the original game.
+    #[serde(alias = "per-shot")]
+    PerShot,
+}
+
+/// The rules for where hazards are placed in a generated cave. Nothing is ever placed in the
+/// starting room.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Placement {
+    /// Whether hazards can go next to the starting room even while there's space elsewhere
+    pub near_start: bool,
+    /// Whether bats and pits can share a room. Wumpuses can always end up anywhere.
+    pub shared: bool,
+}
+
+/// The reasons that a set of rules can't be used
+#[derive(Debug)]
+pub enum RulesError {
+    /// The rules file couldn't be parsed
+    Format(toml::de::Error),
+    /// The rules were parsed, but don't make sense
+    Invalid(&'static str),
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            arrow_cost: ArrowCost::PerRoom,
+            hazards: Hazards::default(),
+            placement: Placement::default(),
+            recover_arrows: false,
+            wumpus: Profile::Sleeper,
+            wumpus_move_prob: WUMPUS_MOVE_PROB,
+            starting_arrows: STARTING_ARROWS,
+            min_shot: 1,
+            max_shot: 5,
+            bats_into_hazards: true,
+            classic_cave: false,
+        }
+    }
+}
+
+impl Rules {
+    /// The names of the built-in rules, for `preset`
+    pub const PRESETS: [&'static str; 5] = ["easy", "normal", "classic", "hard", "nightmare"];
+
+    /// The rules of Gregory Yob's original game
+    pub fn classic() -> Self {
+        Self {
+            arrow_cost: ArrowCost::PerShot,
+            hazards: Hazards {
+                bats: 2,
+                pits: 2,
+                wumpuses: 1,
+            },
+            classic_cave: true,
+            ..Self::default()
+        }
+    }
+
+    /// Gives one of the built-in sets of rules, by name
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Self {
+                arrow_cost: ArrowCost::PerShot,
+                recover_arrows: true,
+                wumpus_move_prob: 0.5,
+                starting_arrows: 8,
+                bats_into_hazards: false,
+                ..Self::default()
+            }),
+            "normal" => Some(Self::default()),
+            "classic" => Some(Self::classic()),
+            "hard" => Some(Self {
+                hazards: Hazards {
+                    bats: 2,
+                    pits: 2,
+                    wumpuses: 1,
+                },
+                wumpus: Profile::Wanderer {
+                    every: Profile::DEFAULT_WANDER,
+                },
+                starting_arrows: 4,
+                ..Self::default()
+            }),
+            "nightmare" => Some(Self {
+                hazards: Hazards {
+                    bats: 3,
+                    pits: 3,
+                    wumpuses: 2,
+                },
+                placement: Placement {
+                    near_start: true,
+                    shared: false,
+                },
+                wumpus: Profile::Hunter,
+                wumpus_move_prob: 0.9,
+                starting_arrows: 3,
+                max_shot: 3,
+                ..Self::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Parses rules written as TOML, and checks that they can be played
+    pub fn from_toml(s: &str) -> Result<Self, RulesError> {
+        let rules: Self = toml::from_str(s).map_err(RulesError::Format)?;
+        rules.check()?;
+        Ok(rules)
+    }
+
+    /// Checks that the rules make sense, apart from whether the hazards fit in any particular
+    /// cave
+    pub fn check(&self) -> Result<(), RulesError> {
+        let invalid = |msg| Err(RulesError::Invalid(msg));
+
+        if self.hazards.wumpuses == 0 {
+            invalid("there must be at least one wumpus")
+        } else if self.starting_arrows <= 0 {
+            invalid("the player must start with at least one arrow")
+        } else if self.min_shot == 0 || self.min_shot > self.max_shot {
+            invalid("the shortest shot must be at least 1, and no longer than the longest")
+        } else if self.shot_cost(self.min_shot as i32) > self.starting_arrows {
+            invalid("the player must start with enough arrows for the shortest shot")
+        } else if !(0.0..=1.0).contains(&self.wumpus_move_prob) {
+            invalid("the chance of the wumpus moving must be between 0 and 1")
+        } else if self.classic_cave && !self.fit(20) {
+            invalid("there isn't enough room for all of the hazards in the classic cave")
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns whether there's room for all of the hazards in a cave with `n_rooms` rooms
+    pub fn fit(&self, n_rooms: usize) -> bool {
+        let h = &self.hazards;
+        if self.classic_cave {
+            // Everything in the classic cave gets its own room
+            h.bats + h.pits + h.wumpuses < n_rooms
+        } else if self.placement.shared {
+            let available = n_rooms.saturating_sub(1);
+            h.bats <= available && h.pits <= available && h.wumpuses <= available
+        } else {
+            h.fit(n_rooms)
+        }
+    }
+
+    /// Returns the number of arrows a shot through `dist` rooms will use up
+    pub fn shot_cost(&self, dist: i32) -> i32 {
+        match self.arrow_cost {
+            ArrowCost::PerRoom => dist,
+            ArrowCost::PerShot => 1,
+        }
+    }
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RulesError::Format(e) => write!(f, "malformed rules: {}", e),
+            RulesError::Invalid(msg) => write!(f, "invalid rules: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RulesError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_valid() {
+        for name in Rules::PRESETS.iter() {
+            let rules = Rules::preset(name).unwrap();
+            assert!(rules.check().is_ok(), "{}", name);
+        }
+        assert_eq!(Rules::preset("impossible"), None);
+    }
+
+    #[test]
+    fn missing_fields_come_from_normal() {
+        let rules = Rules::from_toml(
+            r#"
+            starting_arrows = 3
+            arrow_cost = "per-shot"
+            wumpus = "wanderer:2"
+
+            [hazards]
+            pits = 3
+            "#,
+        )
+        .unwrap();
+
+        let normal = Rules::default();
+        assert_eq!(rules.starting_arrows, 3);
+        assert_eq!(rules.arrow_cost, ArrowCost::PerShot);
+        assert_eq!(rules.wumpus, Profile::Wanderer { every: 2 });
+        assert_eq!(rules.hazards.pits, 3);
+        assert_eq!(rules.hazards.bats, normal.hazards.bats);
+        assert_eq!(rules.max_shot, normal.max_shot);
+        assert_eq!(rules.wumpus_move_prob, normal.wumpus_move_prob);
+        assert_eq!(rules.placement, normal.placement);
+    }
+
+    #[test]
+    fn bad_rules_are_rejected() {
+        let invalid = [
+            "min_shot = 4\nmax_shot = 2",
+            "wumpus_move_prob = 1.5",
+            "starting_arrows = 0",
+        ];
+        for toml in invalid.iter() {
+            match Rules::from_toml(toml) {
+                Err(RulesError::Invalid(_)) => (),
+                other => panic!("expected '{}' to be invalid, got {:?}", toml, other),
+            }
+        }
+
+        let malformed = ["arrow_cost = \"per-arrow\"", "wumpus = \"lurker\"", "speed = 3"];
+        for toml in malformed.iter() {
+            match Rules::from_toml(toml) {
+                Err(RulesError::Format(_)) => (),
+                other => panic!("expected '{}' to be malformed, got {:?}", toml, other),
+            }
+        }
+    }
+}
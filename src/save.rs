@@ -0,0 +1,174 @@
+//! Saving games to, and loading them from, JSON files
+//!
+//! A save file holds the entire `Game` - including the state of its random number generator - so
+//! a loaded game will continue exactly as the original would have. Replays use the same
+//! versioning, so they share `SAVE_VERSION`. The player's other data files are written the same
+//! way, but each has its own version, so that they don't all become unreadable whenever saved
+//! games change.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::game::Game;
+
+/// The current version of the save and replay formats. This should be incremented whenever either
+/// format changes in a way that older versions of the game can't read.
+pub const SAVE_VERSION: u32 = 3;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    /// The file has a version that this version of the game can't read
+    UnsupportedVersion(u32),
+    /// The file was well-formed, but doesn't describe a valid game
+    Invalid(&'static str),
+}
+
+// Every file we write is a JSON object with the version alongside the rest of its fields
+#[derive(Serialize, Deserialize)]
+struct Versioned<T> {
+    version: u32,
+    #[serde(flatten)]
+    inner: T,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile<G> {
+    game: G,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "{}", e),
+            SaveError::Format(e) => write!(f, "malformed file: {}", e),
+            SaveError::UnsupportedVersion(v) => {
+                write!(f, "the file has version {}, which this version of the game can't read", v)
+            }
+            SaveError::Invalid(msg) => write!(f, "invalid save file: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Format(e)
+    }
+}
+
+pub(crate) fn write_versioned<W, T>(mut writer: W, version: u32, inner: T) -> Result<(), SaveError>
+where
+    W: Write,
+    T: Serialize,
+{
+    let file = Versioned { version, inner };
+
+    serde_json::to_writer_pretty(&mut writer, &file)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+pub(crate) fn read_versioned<R, T>(reader: R, version: u32) -> Result<T, SaveError>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    // The version is checked before anything else, so that files from other versions give a
+    // useful error instead of whatever happens to fail first
+    let file: Versioned<serde_json::Value> = serde_json::from_reader(reader)?;
+    if file.version != version {
+        return Err(SaveError::UnsupportedVersion(file.version));
+    }
+
+    Ok(serde_json::from_value(file.inner)?)
+}
+
+// Checks that a game we've read from a file won't cause any out-of-bounds indexing
+pub(crate) fn check_game(game: &Game) -> Result<(), SaveError> {
+    let maze = game.maze();
+    let n = maze.rooms.len();
+    if n < 4 {
+        Err(SaveError::Invalid("there must be at least four rooms"))
+    } else if maze.rooms.iter().flatten().any(|(r, _)| *r >= n) {
+        Err(SaveError::Invalid("a tunnel leads to a room that doesn't exist"))
+    } else if (maze.bats.iter().chain(&maze.pits).chain(&maze.wumpuses))
+        .chain(&[game.player(), maze.start])
+        .any(|&r| r >= n)
+    {
+        Err(SaveError::Invalid("the player or a hazard is outside the cave"))
+    } else if maze.dropped.keys().any(|&r| r >= n) {
+        Err(SaveError::Invalid("arrows were dropped outside the cave"))
+    } else {
+        Ok(())
+    }
+}
+
+impl Game {
+    /// Writes the full state of the game
+    pub fn save<W: Write>(&self, writer: W) -> Result<(), SaveError> {
+        write_versioned(writer, SAVE_VERSION, SaveFile { game: self })
+    }
+
+    /// Reads a game that was previously written with `save`
+    pub fn load<R: Read>(reader: R) -> Result<Game, SaveError> {
+        let SaveFile { game } = read_versioned(reader, SAVE_VERSION)?;
+        check_game(&game)?;
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Action;
+    use crate::rules::Rules;
+
+    fn saved(game: &Game) -> Vec<u8> {
+        let mut out = Vec::new();
+        game.save(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut game = Game::from_seed(10, Rules::preset("easy").unwrap(), 5);
+        for i in 0..5 {
+            let direction = game.maze().rooms[game.player()][i % 3].1;
+            let _ = game.act(Action::Move(direction));
+        }
+
+        let file = saved(&game);
+        let mut loaded = Game::load(&file[..]).unwrap();
+        assert_eq!(saved(&loaded), file);
+
+        // The random number generator comes back too, so the game carries on the same way
+        for i in 0..20 {
+            let direction = game.maze().rooms[game.player()][i % 3].1;
+            let action = Action::Move(direction);
+            assert_eq!(game.act(action.clone()), loaded.act(action));
+        }
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let file = saved(&Game::from_seed(2, Rules::default(), 1));
+        let mut json: serde_json::Value = serde_json::from_slice(&file).unwrap();
+        json["version"] = 1.into();
+
+        match Game::load(json.to_string().as_bytes()) {
+            Err(SaveError::UnsupportedVersion(1)) => (),
+            other => panic!("expected an unsupported version, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Scoring games that were won, and keeping a table of the best scores
+//!
+//! There's a separate table for every set of rules and cave size, since scores from different
+//! ones can't be fairly compared.
+
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+use crate::behaviour::Profile;
+use crate::game::Game;
+use crate::rules::{ArrowCost, Rules};
+use crate::save::{read_versioned, write_versioned, SaveError};
+
+/// The most scores kept in each table
+pub const TABLE_SIZE: usize = 10;
+
+/// The current version of the high score file. This is separate from `SAVE_VERSION`, and should
+/// only be incremented when the high scores themselves change in a way that can't be read.
+pub const SCORES_VERSION: u32 = 1;
+
+/// A single entry in a high score table
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+    pub points: u32,
+    /// The seed of the game, so that it can be played again
+    pub seed: u64,
+    pub turns: u32,
+    pub arrows_left: i32,
+    pub rooms_visited: usize,
+}
+
+/// The best scores for one set of rules and cave size, from highest to lowest
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Table {
+    /// The name of the rules, from `Rules::name`
+    pub rules: String,
+    pub rooms: usize,
+    pub scores: Vec<Score>,
+}
+
+/// Every high score table
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScores {
+    tables: Vec<Table>,
+}
+
+impl Rules {
+    /// A rough measure of how hard the rules are, which scores are multiplied by. The normal
+    /// rules are 1.
+    pub fn difficulty(&self) -> f32 {
+        let normal = Rules::default();
+        let h = &self.hazards;
+        let mut d = 1.0
+            + 0.1 * (h.pits as f32 - normal.hazards.pits as f32)
+            + 0.05 * (h.bats as f32 - normal.hazards.bats as f32)
+            + 0.5 * (h.wumpuses as f32 - normal.hazards.wumpuses as f32)
+            + 0.1 * (normal.starting_arrows - self.starting_arrows) as f32
+            + 0.05 * (normal.max_shot as f32 - self.max_shot as f32)
+            + 0.5 * (self.wumpus_move_prob - normal.wumpus_move_prob);
+
+        d += match self.wumpus {
+            Profile::Sleeper => 0.0,
+            Profile::Coward => 0.1,
+            Profile::Wanderer { .. } => 0.25,
+            Profile::Hunter => 0.5,
+        };
+        if self.arrow_cost == ArrowCost::PerShot {
+            d -= 0.25;
+        }
+        if self.recover_arrows {
+            d -= 0.25;
+        }
+        if !self.bats_into_hazards {
+            d -= 0.1;
+        }
+        if self.placement.near_start {
+            d += 0.1;
+        }
+
+        d.max(0.25)
+    }
+
+    /// The name of the preset these rules match, or otherwise a name made from a hash of the
+    /// rules, so that the same rules file always gets the same name
+    pub fn name(&self) -> String {
+        let preset = Rules::PRESETS.iter().find(|&&p| Rules::preset(p).as_ref() == Some(self));
+        if let Some(name) = preset {
+            return name.to_string();
+        }
+
+        // FNV-1a, which unlike the standard library's hasher is the same everywhere
+        let json = serde_json::to_string(self).expect("rules can always be serialized");
+        let hash = json.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        format!("custom-{:08x}", hash as u32)
+    }
+}
+
+impl Game {
+    /// The score for a game that was won. Bigger caves and harder rules are worth more, as are
+    /// exploring, having arrows left over, and finishing in under two turns per room.
+    pub fn score(&self) -> Option<u32> {
+        if !self.won() {
+            return None;
+        }
+
+        let rooms = self.maze().rooms.len() as f32;
+        let speed = (2.0 * rooms - self.turn() as f32).max(0.0);
+        let points = 10.0 * rooms
+            + 5.0 * self.visited().len() as f32
+            + 25.0 * self.maze().arrows as f32
+            + 5.0 * speed;
+
+        Some((points * self.rules().difficulty()).round() as u32)
+    }
+
+    /// The entry for this game in a high score table, if it was won, has a seed, and never had
+    /// any turns taken back
+    pub fn high_score(&self) -> Option<Score> {
+        if self.rewound() {
+            return None;
+        }
+
+        Some(Score {
+            points: self.score()?,
+            seed: self.seed()?,
+            turns: self.turn(),
+            arrows_left: self.maze().arrows,
+            rooms_visited: self.visited().len(),
+        })
+    }
+}
+
+impl HighScores {
+    pub fn save<W: Write>(&self, writer: W) -> Result<(), SaveError> {
+        write_versioned(writer, SCORES_VERSION, self)
+    }
+
+    pub fn load<R: Read>(reader: R) -> Result<HighScores, SaveError> {
+        read_versioned(reader, SCORES_VERSION)
+    }
+
+    /// Every table, in the order they were first added to
+    pub fn tables(&self) -> &[Table] {
+        &self.tables
+    }
+
+    /// Adds a score to the table for the given rules and cave size, returning its position
+    /// (starting from 0), or `None` if it wasn't good enough to be kept
+    pub fn add(&mut self, rules: &str, rooms: usize, score: Score) -> Option<usize> {
+        let idx = match self.tables.iter().position(|t| t.rules == rules && t.rooms == rooms) {
+            Some(idx) => idx,
+            None => {
+                self.tables.push(Table {
+                    rules: rules.to_string(),
+                    rooms,
+                    scores: Vec::new(),
+                });
+                self.tables.len() - 1
+            }
+        };
+
+        // Ties go to whoever got there first
+        let scores = &mut self.tables[idx].scores;
+        let pos = scores.iter().position(|s| s.points < score.points).unwrap_or(scores.len());
+        if pos >= TABLE_SIZE {
+            return None;
+        }
+
+        scores.insert(pos, score);
+        scores.truncate(TABLE_SIZE);
+        Some(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(points: u32, seed: u64) -> Score {
+        Score {
+            points,
+            seed,
+            turns: 10,
+            arrows_left: 1,
+            rooms_visited: 5,
+        }
+    }
+
+    #[test]
+    fn scores_are_kept_in_order() {
+        let mut scores = HighScores::default();
+        assert_eq!(scores.add("normal", 20, score(100, 1)), Some(0));
+        assert_eq!(scores.add("normal", 20, score(300, 2)), Some(0));
+        assert_eq!(scores.add("normal", 20, score(200, 3)), Some(1));
+        // Ties go after the scores that were there first
+        assert_eq!(scores.add("normal", 20, score(200, 4)), Some(2));
+
+        let seeds: Vec<u64> = scores.tables()[0].scores.iter().map(|s| s.seed).collect();
+        assert_eq!(seeds, vec![2, 3, 4, 1]);
+    }
+
+    #[test]
+    fn file_has_its_own_version() {
+        let mut scores = HighScores::default();
+        scores.add("normal", 20, score(100, 1));
+        let mut file = Vec::new();
+        scores.save(&mut file).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&file).unwrap();
+        assert_eq!(json["version"], SCORES_VERSION);
+        assert_eq!(HighScores::load(&file[..]).unwrap(), scores);
+    }
+
+    #[test]
+    fn tables_are_separate_and_full_ones_drop_the_lowest() {
+        let mut scores = HighScores::default();
+        for i in 0..TABLE_SIZE as u32 {
+            scores.add("normal", 20, score(100 + i, i as u64));
+        }
+        assert_eq!(scores.add("normal", 20, score(100, 99)), None);
+        assert_eq!(scores.add("normal", 20, score(150, 99)), Some(0));
+        assert_eq!(scores.add("normal", 40, score(1, 99)), Some(0));
+        assert_eq!(scores.add("hard", 20, score(1, 99)), Some(0));
+
+        let table = &scores.tables()[0];
+        assert_eq!(scores.tables().len(), 3);
+        assert_eq!(table.scores.len(), TABLE_SIZE);
+        assert_eq!(table.scores.last().unwrap().points, 101);
+    }
+}
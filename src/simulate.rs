@@ -0,0 +1,321 @@
+//! Playing lots of games automatically, to see how winnable a cave size and set of rules are
+
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::deduce::{Hazard, Status};
+use crate::game::{Action, Event, Game};
+use crate::maze::Direction;
+use crate::rng::GameRng;
+use crate::rules::Rules;
+use crate::solve::Solver;
+
+/// Games that go on longer than this are given up on
+pub const TURN_LIMIT: u32 = 1000;
+
+// Mixed into the seed of each game to give the policy its own random numbers, so that its choices
+// don't change the way the game itself plays out
+const POLICY_SEED: u64 = 0x9c1f_3a6e_d2b4_7085;
+// How often the cautious policy walks into a room it's been warned about, when it's run out of
+// better ideas
+const CAUTIOUS_GAMBLE: f32 = 0.1;
+
+/// The ways of playing that can be simulated
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// Takes a random tunnel every turn, and shoots into a random neighbouring room when it
+    /// smells a wumpus
+    Random,
+    /// Only looks at the neighbouring rooms: shoots a wumpus that must be next door, and
+    /// otherwise prefers rooms that are safe, then ones that nothing has been warned about
+    Cautious,
+    /// The `Solver`
+    Solver,
+}
+
+/// How a game ended
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Outcome {
+    Won,
+    FellInPit,
+    Eaten,
+    OutOfArrows,
+    ShotYourself,
+    /// The player quit, or in a simulation, the game hit `TURN_LIMIT` or the policy had no way
+    /// left to win
+    GaveUp,
+}
+
+/// The results of a batch of games
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub games: u32,
+    /// The number of games that ended each way, indexed by `Outcome as usize`
+    pub outcomes: [u32; 6],
+    pub turns: u64,
+    /// The number of times bats carried the player off
+    pub bat_drops: u32,
+    /// How many of those drops ended in a hazard, or next to a pit or a wumpus
+    pub dangerous_drops: u32,
+}
+
+impl Policy {
+    /// Picks the next action in the game, or `None` to give up
+    pub fn decide(self, game: &Game, rng: &mut GameRng) -> Option<Action> {
+        match self {
+            Policy::Random => Some(random_action(game, rng)),
+            Policy::Cautious => Some(cautious_action(game, rng)),
+            Policy::Solver => Solver.decide(game).map(|d| d.action),
+        }
+    }
+}
+
+impl Outcome {
+    pub const ALL: [Outcome; 6] = [
+        Outcome::Won,
+        Outcome::FellInPit,
+        Outcome::Eaten,
+        Outcome::OutOfArrows,
+        Outcome::ShotYourself,
+        Outcome::GaveUp,
+    ];
+
+    /// How the game ended, if it's over
+    pub fn of(game: &Game) -> Option<Self> {
+        if game.is_over() {
+            Some(Self::from_events(game.ending().as_slice()))
+        } else {
+            None
+        }
+    }
+
+    /// Works out how a game ended from the events of its last turn
+    pub fn from_events(events: &[Event]) -> Self {
+        match events.iter().find(|e| e.is_fatal()) {
+            Some(Event::WumpusKilled) => Outcome::Won,
+            Some(Event::FellInPit) => Outcome::FellInPit,
+            Some(Event::EatenByWumpus) => Outcome::Eaten,
+            Some(Event::OutOfArrows) => Outcome::OutOfArrows,
+            Some(Event::ShotYourself) | Some(Event::ShotYourselfAndWumpus) => Outcome::ShotYourself,
+            _ => Outcome::GaveUp,
+        }
+    }
+}
+
+impl Tally {
+    /// The number of games that ended the given way
+    pub fn count(&self, outcome: Outcome) -> u32 {
+        self.outcomes[outcome as usize]
+    }
+
+    /// The average number of turns taken, over all of the games
+    pub fn average_turns(&self) -> f64 {
+        self.turns as f64 / self.games.max(1) as f64
+    }
+
+    fn merge(&mut self, other: &Tally) {
+        self.games += other.games;
+        for (a, b) in self.outcomes.iter_mut().zip(other.outcomes.iter()) {
+            *a += b;
+        }
+        self.turns += other.turns;
+        self.bat_drops += other.bat_drops;
+        self.dangerous_drops += other.dangerous_drops;
+    }
+
+    fn add(&mut self, outcome: Outcome, turns: u32) {
+        self.games += 1;
+        self.outcomes[outcome as usize] += 1;
+        self.turns += turns as u64;
+    }
+}
+
+/// Plays `games` games in generated caves with `n_rooms` rooms, using the seeds from `seed`
+/// onwards. The rules mustn't ask for the classic cave.
+///
+/// The games are shared out between as many threads as there are CPUs. Each game only depends
+/// on its seed, so the results are the same however they're shared out.
+pub fn simulate(rules: &Rules, n_rooms: usize, policy: Policy, seed: u64, games: u32) -> Tally {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get() as u32);
+    let per_thread = games.div_ceil(threads);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..games)
+            .step_by(per_thread.max(1) as usize)
+            .map(|first| {
+                let count = per_thread.min(games - first);
+                let seed = seed.wrapping_add(first as u64);
+                scope.spawn(move || play_games(rules, n_rooms, policy, seed, count))
+            })
+            .collect();
+
+        let mut tally = Tally::default();
+        for handle in handles {
+            tally.merge(&handle.join().unwrap());
+        }
+        tally
+    })
+}
+
+fn play_games(rules: &Rules, n_rooms: usize, policy: Policy, seed: u64, games: u32) -> Tally {
+    let adds = (n_rooms as u32 - 4) / 2;
+    let mut tally = Tally::default();
+
+    for i in 0..games {
+        let seed = seed.wrapping_add(i as u64);
+        let mut game = Game::from_seed(adds, rules.clone(), seed);
+        let mut rng = GameRng::from_seed(seed ^ POLICY_SEED);
+        let mut outcome = Outcome::GaveUp;
+
+        while !game.is_over() && game.turn() < TURN_LIMIT {
+            let action = match policy.decide(&game, &mut rng) {
+                Some(action) => action,
+                None => break,
+            };
+            // Policies only pick actions that are allowed, so a rejected one means the policy
+            // has nothing sensible left to do
+            let events = match game.act(action) {
+                Ok(events) => events,
+                Err(_) => break,
+            };
+
+            count_drops(&mut tally, &events);
+            if game.is_over() {
+                outcome = Outcome::from_events(&events);
+            }
+        }
+
+        tally.add(outcome, game.turn());
+    }
+
+    tally
+}
+
+// Counts the times that bats carried the player off, and whether the last drop put them in
+// danger. Bats can drop the player into another bats' room, in which case only the final drop
+// is judged.
+fn count_drops(tally: &mut Tally, events: &[Event]) {
+    let last = match events.iter().rposition(|e| matches!(e, Event::BatsCarriedYou { .. })) {
+        Some(i) => i,
+        None => return,
+    };
+
+    tally.bat_drops += events
+        .iter()
+        .filter(|e| matches!(e, Event::BatsCarriedYou { .. }))
+        .count() as u32;
+
+    let danger = [Event::FellInPit, Event::EatenByWumpus, Event::FeelWind, Event::SmellWumpus];
+    if events[last + 1..].iter().any(|e| danger.contains(e)) {
+        tally.dangerous_drops += 1;
+    }
+}
+
+fn pick<T: Copy>(rng: &mut GameRng, from: &[T]) -> T {
+    from[(rng.gen::<f32>() * from.len() as f32) as usize]
+}
+
+// A shot into a neighbouring room, if the rules allow one
+fn shot_next_door(game: &Game, room: usize) -> Option<Action> {
+    let rules = game.rules();
+    if rules.min_shot <= 1 && rules.shot_cost(1) <= game.maze().arrows {
+        Some(Action::ShootRooms(vec![room]))
+    } else {
+        None
+    }
+}
+
+fn random_action(game: &Game, rng: &mut GameRng) -> Action {
+    let here = &game.maze().rooms[game.player()];
+    let (room, direction) = pick(rng, here);
+
+    if game.senses().contains(&Event::SmellWumpus) && rng.gen::<bool>() {
+        if let Some(shot) = shot_next_door(game, room) {
+            return shot;
+        }
+    }
+    Action::Move(direction)
+}
+
+fn cautious_action(game: &Game, rng: &mut GameRng) -> Action {
+    let rooms = &game.maze().rooms;
+    let knowledge = game.knowledge();
+    let here = &rooms[game.player()];
+    let status = |r: usize, h: Hazard| knowledge.status(rooms, r, h);
+
+    for &(r, _) in here.iter() {
+        if status(r, Hazard::Wumpus) == Status::Present {
+            if let Some(shot) = shot_next_door(game, r) {
+                return shot;
+            }
+        }
+    }
+
+    // Somewhere new that's safe, or failing that somewhere new that nothing has been warned
+    // about. Otherwise, it goes back the way it came, except for the odd gamble on a room that
+    // it's been warned about.
+    let visited = game.visited();
+    let safe = |r: usize| knowledge.is_safe(rooms, r) && !visited.contains(&r);
+    let unwarned = |r: usize| {
+        !visited.contains(&r)
+            && Hazard::ALL.iter().all(|&h| matches!(status(r, h), Status::Absent | Status::Unknown))
+    };
+    let towards = |keep: &dyn Fn(usize) -> bool| -> Vec<Direction> {
+        here.iter().filter(|&&(r, _)| keep(r)).map(|&(_, d)| d).collect()
+    };
+
+    let mut dirs = towards(&safe);
+    if dirs.is_empty() {
+        dirs = towards(&unwarned);
+    }
+    if dirs.is_empty() {
+        let gamble = rng.gen::<f32>() < CAUTIOUS_GAMBLE;
+        dirs = towards(&|r| visited.contains(&r) != gamble);
+    }
+    if dirs.is_empty() {
+        dirs = towards(&|_| true);
+    }
+    Action::Move(pick(rng, &dirs))
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Policy::Random => "random",
+            Policy::Cautious => "cautious",
+            Policy::Solver => "solver",
+        };
+
+        f.write_str(s)
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "random" => Ok(Policy::Random),
+            "cautious" => Ok(Policy::Cautious),
+            "solver" => Ok(Policy::Solver),
+            _ => Err(format!("unknown policy '{}', expected random, cautious or solver", s)),
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Outcome::Won => "won",
+            Outcome::FellInPit => "fell into a pit",
+            Outcome::Eaten => "eaten by a wumpus",
+            Outcome::OutOfArrows => "ran out of arrows",
+            Outcome::ShotYourself => "shot itself",
+            Outcome::GaveUp => "gave up",
+        };
+
+        f.write_str(s)
+    }
+}
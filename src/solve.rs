@@ -0,0 +1,400 @@
+//! A bot that plays the game by itself
+//!
+//! The solver only uses what a player could know: the tunnels out of the rooms it's been in, and
+//! what the `Knowledge` from the warnings says about the rest. It only ever walks into rooms that
+//! have been proven safe, and shoots as soon as a wumpus has been narrowed down to a single room.
+//! When there's nothing safe left to do, it takes whichever risk looks smallest.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::deduce::{Hazard, Status};
+use crate::game::{Action, Game};
+use crate::maze::Direction;
+
+// How much worse falling into a pit or meeting a wumpus is than being carried off by bats, when
+// weighing up risks
+const BATS_WEIGHT: f32 = 0.25;
+// The smallest chance of hitting a wumpus that's worth an arrow, when it hasn't been found
+const SHOT_GAMBLE: f32 = 0.5;
+
+/// An action chosen by the `Solver`, with the reason for it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    pub action: Action,
+    pub reason: String,
+}
+
+/// Picks actions for a game, one turn at a time
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Solver;
+
+// The rooms the solver can safely reach, found by a breadth-first search from the player
+struct Reach {
+    // For every room that's been found, the room and direction it was first reached from. The
+    // player's room isn't included.
+    came_from: BTreeMap<usize, (usize, Direction)>,
+    // The safe rooms that haven't been visited, in order of distance
+    unexplored: Vec<usize>,
+    // Rooms next to the safe ones that aren't known to be safe themselves
+    frontier: Vec<usize>,
+}
+
+impl Solver {
+    /// Decides what to do next in the game, which mustn't be over. Gives `None` if there's no
+    /// way left to win, because there aren't enough arrows for even the shortest shot and none
+    /// that can be picked up.
+    pub fn decide(&self, game: &Game) -> Option<Decision> {
+        let rules = game.rules();
+        let arrows = game.maze().arrows;
+        let reach = Reach::search(game);
+
+        // Arrows that missed can still be picked up, as long as there's a way to get to them
+        if rules.shot_cost(rules.min_shot as i32) > arrows {
+            let risk = |r: usize| risk(game, r);
+            let dropped = game.maze().dropped.keys().filter(|r| reach.came_from.contains_key(r));
+            let &r = dropped.min_by(|&&a, &&b| risk(a).partial_cmp(&risk(b)).unwrap())?;
+            let reason = format!("Out of arrows, so going to pick some up in room {}", r);
+            return Some(reach.walk_to(game, r, reason));
+        }
+
+        let rooms = &game.maze().rooms;
+        let knowledge = game.knowledge();
+
+        let located: Vec<usize> = (0..rooms.len())
+            .filter(|&r| knowledge.status(rooms, r, Hazard::Wumpus) == Status::Present)
+            .collect();
+        for &w in &located {
+            if let Some(path) = shot_path(game, w) {
+                return Some(Decision {
+                    action: Action::ShootRooms(path),
+                    reason: format!(
+                        "A wumpus has to be in room {}: it's the only room left that could explain \
+                         a smell.",
+                        w
+                    ),
+                });
+            }
+        }
+
+        // A wumpus that's been found but is out of range can be walked towards, as long as the
+        // way there is safe
+        for &w in &located {
+            let next_to = |r: usize| rooms[w].iter().any(|&(n, _)| n == r);
+            let safe = |r: usize| knowledge.is_safe(rooms, r);
+            if next_to(game.player()) {
+                continue;
+            } else if let Some(&r) = reach.came_from.keys().find(|&&r| next_to(r) && safe(r)) {
+                let reason = format!("Getting into range of the wumpus in room {}", w);
+                return Some(reach.walk_to(game, r, reason));
+            }
+        }
+
+        if let Some(&r) = reach.unexplored.first() {
+            let reason = format!("Room {} has been ruled out for every hazard, so it's safe", r);
+            return Some(reach.walk_to(game, r, reason));
+        }
+
+        // Nothing is safe, so it's time to gamble: either on a shot, or on a room. A shot that
+        // might miss is only worth it if it leaves an arrow for another try.
+        let wumpus_chance = |r: usize| knowledge.chance(rooms, r, Hazard::Wumpus, 0.0);
+        let best_shot = (0..rooms.len())
+            .filter(|&r| r != game.player() && wumpus_chance(r) >= SHOT_GAMBLE)
+            .filter_map(|r| shot_path(game, r).map(|path| (wumpus_chance(r), r, path)))
+            .filter(|(_, _, path)| rules.shot_cost(path.len() as i32) < arrows)
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        if let Some((chance, r, path)) = best_shot {
+            return Some(Decision {
+                action: Action::ShootRooms(path),
+                reason: format!(
+                    "There's nowhere safe left to go, but room {} has a {:.0}% chance of holding a \
+                     wumpus, so it's worth an arrow.",
+                    r,
+                    chance * 100.0
+                ),
+            });
+        }
+
+        let risk = |r: usize| risk(game, r);
+        Some(match reach.frontier.iter().min_by(|&&a, &&b| risk(a).partial_cmp(&risk(b)).unwrap()) {
+            Some(&r) => {
+                let reason = format!(
+                    "There's nowhere safe left to go, so taking the least risky room, {} ({})",
+                    r,
+                    describe_risk(game, r)
+                );
+                reach.walk_to(game, r, reason)
+            }
+            // Every room has been visited and is safe, which can't happen while a wumpus is
+            // still alive - but just in case, the player wanders
+            None => {
+                let direction = rooms[game.player()][0].1;
+                Decision {
+                    action: Action::Move(direction),
+                    reason: "There's nothing left to learn, so wandering.".into(),
+                }
+            }
+        })
+    }
+}
+
+impl Reach {
+    fn search(game: &Game) -> Self {
+        let rooms = &game.maze().rooms;
+        let knowledge = game.knowledge();
+        let visited = game.visited();
+        let safe = |r: usize| knowledge.is_safe(rooms, r);
+
+        let mut reach = Reach {
+            came_from: BTreeMap::new(),
+            unexplored: Vec::new(),
+            frontier: Vec::new(),
+        };
+
+        let start = game.player();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        while let Some(r) = queue.pop_front() {
+            // Only the tunnels out of visited rooms are known
+            if r != start && !visited.contains(&r) {
+                continue;
+            }
+
+            for &(next, dir) in rooms[r].iter() {
+                if next == start || reach.came_from.contains_key(&next) {
+                    continue;
+                }
+                reach.came_from.insert(next, (r, dir));
+
+                if !safe(next) {
+                    reach.frontier.push(next);
+                } else if visited.contains(&next) {
+                    queue.push_back(next);
+                } else {
+                    reach.unexplored.push(next);
+                }
+            }
+        }
+
+        reach
+    }
+
+    // Takes the first step along the way to `target`
+    fn walk_to(&self, game: &Game, target: usize, reason: String) -> Decision {
+        let mut room = target;
+        let mut via = Vec::new();
+        let direction = loop {
+            let (from, dir) = self.came_from[&room];
+            if from == game.player() {
+                break dir;
+            }
+            via.push(from);
+            room = from;
+        };
+
+        let reason = match via.last() {
+            None => format!("{}.", reason),
+            Some(next) => format!("{}. Heading there by way of room {}.", reason, next),
+        };
+        Decision {
+            action: Action::Move(direction),
+            reason,
+        }
+    }
+}
+
+// Finds the shortest path an arrow could take to `target`, through rooms whose tunnels are known,
+// if there's one within range
+fn shot_path(game: &Game, target: usize) -> Option<Vec<usize>> {
+    let rooms = &game.maze().rooms;
+    let rules = game.rules();
+    let visited = game.visited();
+    let start = game.player();
+
+    let mut came_from = BTreeMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back((start, 0));
+    while let Some((r, dist)) = queue.pop_front() {
+        if r == target {
+            break;
+        } else if dist == rules.max_shot || (r != start && !visited.contains(&r)) {
+            continue;
+        }
+
+        for &(next, _) in rooms[r].iter() {
+            // The arrow can't come back into the player's room without hitting them
+            if next != start && !came_from.contains_key(&next) {
+                came_from.insert(next, r);
+                queue.push_back((next, dist + 1));
+            }
+        }
+    }
+
+    came_from.get(&target)?;
+    let mut path = vec![target];
+    while let Some(&prev) = came_from.get(path.last().unwrap()) {
+        if prev == start {
+            break;
+        }
+        path.push(prev);
+    }
+    path.reverse();
+
+    let cost = rules.shot_cost(path.len() as i32);
+    if path.len() < rules.min_shot || cost > game.maze().arrows {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+// The chance of each hazard being in a room that nothing is known about
+fn unknown_chance(game: &Game, hazard: Hazard) -> f32 {
+    let maze = game.maze();
+    let count = match hazard {
+        Hazard::Pit => maze.pits.len(),
+        Hazard::Bats => maze.bats.len(),
+        Hazard::Wumpus => maze.wumpuses.len(),
+    };
+    let unvisited = maze.rooms.len().saturating_sub(game.visited().len()).max(1);
+    (count as f32 / unvisited as f32).min(1.0)
+}
+
+fn chance(game: &Game, room: usize, hazard: Hazard) -> f32 {
+    let unknown = unknown_chance(game, hazard);
+    game.knowledge().chance(&game.maze().rooms, room, hazard, unknown)
+}
+
+// How dangerous it looks to walk into a room
+fn risk(game: &Game, room: usize) -> f32 {
+    let [pit, bats, wumpus] = Hazard::ALL.map(|h| chance(game, room, h));
+    1.0 - (1.0 - pit) * (1.0 - wumpus) + BATS_WEIGHT * bats
+}
+
+fn describe_risk(game: &Game, room: usize) -> String {
+    let hazards = [(Hazard::Pit, "a pit"), (Hazard::Wumpus, "a wumpus"), (Hazard::Bats, "bats")];
+    let parts: Vec<String> = hazards
+        .iter()
+        .map(|&(h, name)| (chance(game, room, h), name))
+        .filter(|&(c, _)| c > 0.0)
+        .map(|(c, name)| format!("{:.0}% chance of {}", c * 100.0, name))
+        .collect();
+
+    if parts.is_empty() {
+        "nothing is known about it".into()
+    } else {
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Maze;
+    use crate::rng::GameRng;
+    use crate::rules::{ArrowCost, Rules};
+    use rand::SeedableRng;
+
+    // A triangular prism: rooms 0, 1 and 2 form one end and 3, 4 and 5 the other, with a tunnel
+    // north from each room in the first to the one opposite it
+    const PRISM: &str = r#"
+        [rooms]
+        0 = [[3, "north"], [1, "east"], [2, "west"]]
+        1 = [[4, "north"], [2, "east"], [0, "west"]]
+        2 = [[5, "north"], [0, "east"], [1, "west"]]
+        3 = [[0, "south"], [4, "east"], [5, "west"]]
+        4 = [[1, "south"], [5, "east"], [3, "west"]]
+        5 = [[2, "south"], [3, "east"], [4, "west"]]
+    "#;
+
+    fn prism(hazards: &str, rules: Rules) -> Game {
+        let maze = Maze::from_level(&format!("{}\n{}", hazards, PRISM), 5).unwrap();
+        Game::with_rules(maze, rules, GameRng::from_seed(1))
+    }
+
+    // The room that a move would take the player into
+    fn destination(game: &Game, decision: &Decision) -> usize {
+        match decision.action {
+            Action::Move(d) => game.maze().rooms[game.player()]
+                .iter()
+                .find(|(_, dir)| *dir == d)
+                .unwrap()
+                .0,
+            ref action => panic!("expected a move, got {}", action),
+        }
+    }
+
+    #[test]
+    fn shoots_a_located_wumpus() {
+        let mut game = prism("wumpuses = [3]", Rules::default());
+        // Room 0 smells the wumpus, and room 1 doesn't, which leaves only room 3
+        game.act(Action::Move(Direction::East)).unwrap();
+
+        let decision = Solver.decide(&game).unwrap();
+        assert_eq!(decision.action, Action::ShootRooms(vec![0, 3]));
+    }
+
+    #[test]
+    fn walks_into_safe_rooms() {
+        let mut game = prism("pits = [4]\nwumpuses = [5]", Rules::default());
+        for _ in 0..2 {
+            let decision = Solver.decide(&game).unwrap();
+            let room = destination(&game, &decision);
+            assert!(game.knowledge().is_safe(&game.maze().rooms, room), "room {}", room);
+            game.act(decision.action).unwrap();
+        }
+    }
+
+    #[test]
+    fn shots_are_in_range_and_affordable() {
+        let rules = Rules {
+            arrow_cost: ArrowCost::PerRoom,
+            starting_arrows: 3,
+            max_shot: 4,
+            ..Rules::default()
+        };
+
+        for seed in 0..50 {
+            let mut game = Game::from_seed(20, rules.clone(), seed);
+            for i in 0..10 {
+                let direction = game.maze().rooms[game.player()][i % 3].1;
+                if game.act(Action::Move(direction)).is_err() || game.is_over() {
+                    break;
+                }
+            }
+
+            let rooms = &game.maze().rooms;
+            for target in 0..rooms.len() {
+                let path = match shot_path(&game, target) {
+                    Some(path) => path,
+                    None => continue,
+                };
+                assert!(path.len() <= rules.max_shot && path.len() >= rules.min_shot);
+                assert!(rules.shot_cost(path.len() as i32) <= game.maze().arrows);
+                assert_eq!(path.last(), Some(&target));
+
+                let mut from = game.player();
+                for &r in &path {
+                    assert!(rooms[from].iter().any(|&(next, _)| next == r));
+                    from = r;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn goes_to_pick_up_arrows() {
+        let rules = Rules {
+            recover_arrows: true,
+            wumpus_move_prob: 0.0,
+            ..Rules::default()
+        };
+        let mut game = prism("arrows = 3\nwumpuses = [5]", rules);
+        game.act(Action::ShootRooms(vec![1, 4, 3])).unwrap();
+        assert_eq!(game.maze().arrows, 0);
+
+        let decision = Solver.decide(&game).unwrap();
+        assert_eq!(destination(&game, &decision), 3);
+        game.act(decision.action).unwrap();
+        assert_eq!(game.maze().arrows, 3);
+    }
+}
@@ -0,0 +1,76 @@
+//! Totals kept across every game the player has finished
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use crate::game::Game;
+use crate::save::{read_versioned, write_versioned, SaveError};
+use crate::simulate::Outcome;
+
+/// The current version of the stats file, which is separate from `SAVE_VERSION`
+pub const STATS_VERSION: u32 = 1;
+
+/// The player's record in caves of one size
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub games: u32,
+    pub wins: u32,
+}
+
+/// Lifetime totals for every finished game
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    pub games: u32,
+    pub wins: u32,
+    pub turns: u64,
+    pub shots: u64,
+    pub bat_carries: u64,
+    /// The record for each cave size, by number of rooms
+    pub sizes: BTreeMap<usize, Record>,
+    /// The number of games that ended each way
+    pub endings: BTreeMap<Outcome, u32>,
+}
+
+impl Stats {
+    pub fn save<W: Write>(&self, writer: W) -> Result<(), SaveError> {
+        write_versioned(writer, STATS_VERSION, self)
+    }
+
+    pub fn load<R: Read>(reader: R) -> Result<Stats, SaveError> {
+        read_versioned(reader, STATS_VERSION)
+    }
+
+    /// Adds a game that's over to the totals. Games that aren't over are ignored.
+    pub fn add(&mut self, game: &Game) {
+        let outcome = match Outcome::of(game) {
+            Some(outcome) => outcome,
+            None => return,
+        };
+        let won = outcome == Outcome::Won;
+
+        self.games += 1;
+        self.wins += won as u32;
+        self.turns += game.turn() as u64;
+        self.shots += game.shots() as u64;
+        self.bat_carries += game.bat_carries() as u64;
+
+        let record = self.sizes.entry(game.maze().rooms.len()).or_default();
+        record.games += 1;
+        record.wins += won as u32;
+        *self.endings.entry(outcome).or_default() += 1;
+    }
+
+    /// The way the player has died most often, and how many times. Ties go to whichever comes
+    /// first in `Outcome`.
+    pub fn most_common_death(&self) -> Option<(Outcome, u32)> {
+        self.endings
+            .iter()
+            .filter(|&(&o, _)| o != Outcome::Won && o != Outcome::GaveUp)
+            .map(|(&o, &n)| (o, n))
+            .fold(None, |best, (o, n)| match best {
+                Some((_, most)) if most >= n => best,
+                _ => Some((o, n)),
+            })
+    }
+}
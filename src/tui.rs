@@ -0,0 +1,355 @@
+//! A full-screen interface, played with the arrow keys
+//!
+//! The terminal is put into raw mode on the alternate screen, and everything is redrawn in place
+//! after each key: the room (or the path of an arrow that's being aimed), a status bar, and a log
+//! of everything that's happened.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event as TermEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Print, PrintStyledContent, Stylize};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use wumpus::Direction::{East, North, South, West};
+use wumpus::{Action, ActionError, Direction, Event, Game, Renderer};
+
+use super::{render_shot, Recorder};
+
+// The most messages to keep in the log
+const LOG_LENGTH: usize = 500;
+
+// Puts the terminal into raw mode on the alternate screen, and puts it back when dropped - even
+// if something panics
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        if let Err(e) = execute!(io::stdout(), EnterAlternateScreen, Hide) {
+            let _ = terminal::disable_raw_mode();
+            return Err(e);
+        }
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// What the keys are currently being used for
+enum Mode {
+    Move,
+    // Aiming an arrow, with the tunnels picked so far and the rooms they lead to
+    Aim {
+        path: Vec<Direction>,
+        rooms: Vec<usize>,
+    },
+    Map,
+    Over,
+}
+
+struct Tui<'r> {
+    game: Game,
+    renderer: &'r dyn Renderer,
+    recorder: Recorder,
+    mode: Mode,
+    log: VecDeque<String>,
+    // The events from the most recent turn, to print once the screen is gone
+    last: Vec<Event>,
+}
+
+/// Plays the game until it's over or the player quits, giving it back at the end along with the
+/// recording of it
+pub fn run(
+    game: Game,
+    renderer: &dyn Renderer,
+    recorder: Recorder,
+) -> io::Result<(Game, Recorder)> {
+    let mut tui = Tui {
+        last: game.senses(),
+        game,
+        renderer,
+        recorder,
+        mode: Mode::Move,
+        log: VecDeque::new(),
+    };
+
+    for e in tui.last.clone() {
+        tui.log(e.to_string());
+    }
+
+    {
+        let _screen = Screen::enter()?;
+        loop {
+            tui.draw()?;
+
+            // Anything else, like the terminal being resized, just needs a redraw
+            if let TermEvent::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Release && !tui.key(key) {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Leave the end of the game on the screen once we're back to normal
+    if tui.game.is_over() {
+        println!("{}", tui.game.render_room(renderer));
+        for e in &tui.last {
+            println!("{}", e);
+        }
+    }
+
+    Ok((tui.game, tui.recorder))
+}
+
+fn arrow_direction(code: KeyCode) -> Option<Direction> {
+    match code {
+        KeyCode::Up => Some(North),
+        KeyCode::Down => Some(South),
+        KeyCode::Left => Some(West),
+        KeyCode::Right => Some(East),
+        _ => None,
+    }
+}
+
+impl Tui<'_> {
+    fn log(&mut self, msg: String) {
+        // Each line of the log is drawn on its own row, so longer messages are split up
+        self.log.extend(msg.lines().map(str::to_string));
+        while self.log.len() > LOG_LENGTH {
+            self.log.pop_front();
+        }
+    }
+
+    // Handles a single key press, returning false if the game should stop
+    fn key(&mut self, key: KeyEvent) -> bool {
+        // Raw mode means that ctrl-c doesn't do anything by itself
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+
+        // The mode is taken out while the key is handled, and put back unless it changes
+        match std::mem::replace(&mut self.mode, Mode::Move) {
+            Mode::Over if key.code == KeyCode::Char('u') && self.recorder.can_rewind() => {
+                self.undo()
+            }
+            Mode::Over => return false,
+            Mode::Map => (),
+            Mode::Move => match (arrow_direction(key.code), key.code) {
+                (Some(d), _) => self.act(Action::Move(d)),
+                (None, KeyCode::Char('s')) => {
+                    let arrows = self.game.maze().arrows;
+                    let rules = self.game.rules();
+                    if rules.shot_cost(rules.min_shot as i32) > arrows {
+                        self.log(ActionError::NotEnoughArrows { have: arrows }.to_string());
+                    } else {
+                        self.mode = Mode::Aim {
+                            path: Vec::new(),
+                            rooms: Vec::new(),
+                        };
+                    }
+                }
+                (None, KeyCode::Char('m')) => self.mode = Mode::Map,
+                (None, KeyCode::Char('a')) => {
+                    let on = !self.game.auto_mark();
+                    self.game.set_auto_mark(on);
+                    self.log(format!("Automatic marking is {}.", if on { "on" } else { "off" }));
+                }
+                (None, KeyCode::Char('u')) if self.recorder.can_rewind() => self.undo(),
+                (None, KeyCode::Char('q')) | (None, KeyCode::Esc) => return false,
+                _ => (),
+            },
+            Mode::Aim { mut path, mut rooms } => {
+                if self.aim(key.code, &mut path, &mut rooms) {
+                    self.mode = Mode::Aim { path, rooms };
+                }
+            }
+        }
+
+        true
+    }
+
+    // Handles a key while aiming an arrow, returning whether the player is still aiming
+    fn aim(&mut self, code: KeyCode, path: &mut Vec<Direction>, rooms: &mut Vec<usize>) -> bool {
+        if let Some(d) = arrow_direction(code) {
+            let from = rooms.last().copied().unwrap_or_else(|| self.game.player());
+            let next = self.game.maze().rooms[from].iter().find(|(_, dir)| *dir == d);
+            let cost = self.game.rules().shot_cost(path.len() as i32 + 1);
+
+            match next {
+                None => self.log("Can't shoot that way, there's a wall!".into()),
+                Some(_) if path.len() == self.game.rules().max_shot || cost > self.game.maze().arrows => {
+                    self.log("The arrow can't go any further.".into())
+                }
+                Some(&(next, _)) => {
+                    path.push(d);
+                    rooms.push(next);
+                }
+            }
+            return true;
+        }
+
+        match code {
+            KeyCode::Enter if path.is_empty() => {
+                self.log("Pick at least one tunnel for the arrow first.".into());
+                true
+            }
+            KeyCode::Enter => {
+                self.act(Action::Shoot(std::mem::take(path)));
+                false
+            }
+            KeyCode::Backspace => {
+                path.pop();
+                rooms.pop();
+                true
+            }
+            KeyCode::Esc => false,
+            _ => true,
+        }
+    }
+
+    fn act(&mut self, action: Action) {
+        let before = self.game.clone();
+        match self.game.act(action.clone()) {
+            Ok(events) => {
+                if let Err(e) = self.recorder.record(before, action.clone(), &events) {
+                    self.log(e);
+                }
+
+                self.log(format!("Turn {}: {}", self.game.turn(), action));
+                for e in &events {
+                    self.log(e.to_string());
+                }
+                self.last = events;
+
+                if self.game.is_over() {
+                    self.mode = Mode::Over;
+                }
+            }
+            Err(ActionError::NoTunnel { .. }) => self.log("Can't go that way!".into()),
+            Err(e) => self.log(e.to_string()),
+        }
+    }
+
+    // Takes back the last turn, in a practice game
+    fn undo(&mut self) {
+        match self.recorder.rewind(1) {
+            Ok(game) => {
+                self.game = game;
+                self.last = self.game.senses();
+                self.log("Took back a turn.".into());
+            }
+            Err(e) => self.log(e),
+        }
+
+        // Undoing the end of the game carries it on, but otherwise it's still over
+        if self.game.is_over() {
+            self.mode = Mode::Over;
+        }
+    }
+
+    fn draw(&self) -> io::Result<()> {
+        let (cols, rows) = terminal::size()?;
+        let (cols, rows) = (cols as usize, rows as usize);
+
+        let picture = match &self.mode {
+            Mode::Aim { rooms, .. } => {
+                let idx = rooms.last().copied().unwrap_or_else(|| self.game.player());
+                render_shot(&self.game, self.renderer, idx, rooms)
+            }
+            Mode::Map => self.game.map(),
+            Mode::Move | Mode::Over => self.game.render_room(self.renderer),
+        };
+
+        let status = format!(
+            " Room {}   Arrows {}   Turn {}",
+            self.game.player(),
+            self.game.maze().arrows,
+            self.game.turn(),
+        );
+
+        let help = match &self.mode {
+            Mode::Move if self.recorder.can_rewind() => {
+                "arrows: move   s: shoot   m: map   a: auto-mark   u: undo   q: quit".to_string()
+            }
+            Mode::Move => "arrows: move   s: shoot   m: map   a: auto-mark   q: quit".to_string(),
+            Mode::Aim { path, .. } => format!(
+                "arrows: pick a tunnel ({} of {})   enter: fire   backspace: undo   esc: cancel",
+                path.len(),
+                self.game.rules().max_shot,
+            ),
+            Mode::Map => "press any key to go back".to_string(),
+            Mode::Over if self.recorder.can_rewind() => {
+                "GAME OVER - u: undo, or any other key to exit".to_string()
+            }
+            Mode::Over => "GAME OVER - press any key to exit".to_string(),
+        };
+
+        let mut out = io::stdout().lock();
+        queue!(out, Clear(ClearType::All))?;
+
+        // The status bar, help and at least one line of the log always get shown, so the
+        // picture is cut off if it doesn't fit
+        let lines: Vec<&str> = picture.lines().collect();
+        let shown = lines.len().min(rows.saturating_sub(3));
+        for (row, line) in lines[..shown].iter().enumerate() {
+            queue!(out, MoveTo(0, row as u16), Print(truncate(line, cols)))?;
+        }
+
+        let mut row = shown;
+        let bar = truncate(&format!("{:<1$}", status, cols), cols);
+        queue!(out, MoveTo(0, row as u16), PrintStyledContent(bar.reverse()))?;
+        row += 1;
+        queue!(out, MoveTo(0, row as u16), Print(truncate(&help, cols)))?;
+        row += 1;
+
+        let space = rows.saturating_sub(row);
+        let skip = self.log.len().saturating_sub(space);
+        for msg in self.log.iter().skip(skip) {
+            queue!(out, MoveTo(0, row as u16), Print(truncate(msg, cols)))?;
+            row += 1;
+        }
+
+        out.flush()
+    }
+}
+
+// Cuts the text off at the width of the terminal, so that it doesn't wrap onto the next line.
+// Colour codes don't take up any room, so they're all kept - including any that come after the
+// cut, which turn the colour back off.
+fn truncate(text: &str, width: usize) -> String {
+    let mut out = String::new();
+    let mut shown = 0;
+    let mut escape = false;
+    for c in text.chars() {
+        escape |= c == '\x1b';
+        if escape {
+            out.push(c);
+            escape = !c.is_ascii_alphabetic();
+        } else if shown < width {
+            out.push(c);
+            shown += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_keeps_colour_codes() {
+        assert_eq!(truncate("abcdef", 4), "abcd");
+        assert_eq!(truncate("ab\x1b[31mcdef\x1b[0m", 3), "ab\x1b[31mc\x1b[0m");
+        assert_eq!(truncate("ab", 10), "ab");
+    }
+}
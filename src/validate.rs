@@ -0,0 +1,112 @@
+//! Checking that a cave is one the game can be played in
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::maze::{Direction, Maze};
+
+/// The ways that a cave can be broken
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MazeError {
+    /// The starting room isn't in the cave
+    NoStart { start: usize },
+    /// A tunnel leads to a room that isn't in the cave
+    NoSuchRoom { from: usize, to: usize },
+    /// A room has more than one tunnel going the same way
+    RepeatedDirection { room: usize, direction: Direction },
+    /// A tunnel doesn't have a matching one coming back
+    NoWayBack { from: usize, to: usize, direction: Direction },
+    /// A tunnel comes back, but not from the opposite direction to the one it left in
+    NotOpposite { from: usize, to: usize, direction: Direction },
+    /// The room can't be reached from the starting room
+    Unreachable { room: usize },
+}
+
+impl Maze {
+    /// Checks that the tunnels make sense: every tunnel leads to a room in the cave and has one
+    /// coming back the opposite way, no room has two tunnels going the same way, and every room
+    /// can be reached from the start. Every cave that `generate` or `expand` makes should pass.
+    pub fn validate(&self) -> Result<(), MazeError> {
+        let n = self.rooms.len();
+        if self.start >= n {
+            return Err(MazeError::NoStart { start: self.start });
+        }
+
+        for (a, room) in self.rooms.iter().enumerate() {
+            for (i, &(b, direction)) in room.iter().enumerate() {
+                if b >= n {
+                    return Err(MazeError::NoSuchRoom { from: a, to: b });
+                } else if room[..i].iter().any(|&(_, d)| d == direction) {
+                    return Err(MazeError::RepeatedDirection { room: a, direction });
+                }
+
+                // Two rooms can be joined by more than one tunnel, so it's the counts going each
+                // way that have to match up
+                let count = |from: usize, to: usize, dir: Option<Direction>| {
+                    self.rooms[from]
+                        .iter()
+                        .filter(|&&(r, d)| r == to && dir.is_none_or(|dir| d == dir))
+                        .count()
+                };
+
+                if count(b, a, None) < count(a, b, None) {
+                    return Err(MazeError::NoWayBack { from: a, to: b, direction });
+                } else if count(b, a, Some(direction.opposite())) != count(a, b, Some(direction)) {
+                    return Err(MazeError::NotOpposite { from: a, to: b, direction });
+                }
+            }
+        }
+
+        let mut seen = vec![false; n];
+        let mut queue = VecDeque::new();
+        seen[self.start] = true;
+        queue.push_back(self.start);
+        while let Some(r) = queue.pop_front() {
+            for &(next, _) in self.rooms[r].iter() {
+                if !seen[next] {
+                    seen[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        match seen.iter().position(|&s| !s) {
+            Some(room) => Err(MazeError::Unreachable { room }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use MazeError::*;
+
+        match self {
+            NoStart { start } => write!(f, "the starting room ({}) isn't in the cave", start),
+            NoSuchRoom { from, to } => {
+                write!(f, "a tunnel from room {} leads to room {}, which doesn't exist", from, to)
+            }
+            RepeatedDirection { room, direction } => {
+                write!(f, "room {} has more than one tunnel going {}", room, direction)
+            }
+            NoWayBack { from, to, direction } => write!(
+                f,
+                "the tunnel {} from room {} leads to room {}, but there's no tunnel back",
+                direction, from, to
+            ),
+            NotOpposite { from, to, direction } => write!(
+                f,
+                "the tunnel {} from room {} leads to room {}, which has no tunnel going {} back",
+                direction,
+                from,
+                to,
+                direction.opposite()
+            ),
+            Unreachable { room } => {
+                write!(f, "room {} can't be reached from the starting room", room)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MazeError {}
@@ -0,0 +1,129 @@
+//! Property tests for the invariants that every cave is meant to keep
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+use rand::SeedableRng;
+
+use wumpus::{Direction, GameRng, Hazards, Maze, MazeError, Placement, Rules};
+
+fn config() -> ProptestConfig {
+    ProptestConfig::with_cases(2000)
+}
+
+fn rules(hazards: Hazards, near_start: bool, shared: bool) -> Rules {
+    Rules {
+        hazards,
+        placement: Placement { near_start, shared },
+        ..Rules::default()
+    }
+}
+
+#[test]
+fn quad_is_valid() {
+    assert_eq!(Maze::quad().validate(), Ok(()));
+}
+
+#[test]
+fn classic_is_valid() {
+    for seed in 0..100 {
+        let maze = Maze::classic(&Rules::classic(), &mut GameRng::from_seed(seed));
+        assert_eq!(maze.validate(), Ok(()));
+    }
+}
+
+proptest! {
+    #![proptest_config(config())]
+
+    #[test]
+    fn generated_caves_are_valid(
+        seed: u64,
+        adds in 0u32..150,
+        bats in 0usize..4,
+        pits in 0usize..4,
+        wumpuses in 1usize..3,
+        near_start: bool,
+        shared: bool,
+    ) {
+        let rules = rules(Hazards { bats, pits, wumpuses }, near_start, shared);
+        let n = 4 + 2 * adds as usize;
+        prop_assume!(rules.fit(n));
+
+        let maze = Maze::generate(adds, &rules, &mut GameRng::from_seed(seed));
+        prop_assert_eq!(maze.rooms.len(), n);
+        prop_assert_eq!(maze.validate(), Ok(()));
+
+        let hazards = maze.bats.iter().chain(&maze.pits).chain(&maze.wumpuses);
+        for &r in hazards {
+            prop_assert!(r < n && r != maze.start, "hazard in room {}", r);
+        }
+    }
+
+    #[test]
+    fn expansions_stay_valid(steps in prop::collection::vec((any::<Index>(), any::<bool>()), 0..200)) {
+        // Any broken expansion is found by checking at the end, and shrinking then finds the
+        // shortest sequence of expansions that breaks the cave
+        let mut maze = Maze::quad();
+        for (idx, rand_bool) in steps {
+            let idx = idx.index(maze.rooms.len());
+            maze.expand(idx, rand_bool);
+        }
+        prop_assert_eq!(maze.validate(), Ok(()));
+    }
+
+    #[test]
+    fn broken_tunnels_are_caught(seed: u64, adds in 0u32..50, room: Index, tunnel in 0usize..3) {
+        let mut maze = Maze::generate(adds, &Rules::default(), &mut GameRng::from_seed(seed));
+        let room = room.index(maze.rooms.len());
+
+        // Turning a tunnel around makes it go the same way as another one in its room, or come
+        // back the wrong way - either way, the cave isn't valid any more
+        let (to, dir) = maze.rooms[room][tunnel];
+        maze.rooms[room][tunnel] = (to, dir.opposite());
+        prop_assert!(maze.validate().is_err());
+    }
+
+    #[test]
+    fn missing_tunnels_are_caught(seed: u64, adds in 0u32..50, room: Index, tunnel in 0usize..3) {
+        let mut maze = Maze::generate(adds, &Rules::default(), &mut GameRng::from_seed(seed));
+        let room = room.index(maze.rooms.len());
+
+        // Pointing the tunnel back into its own room leaves the room it used to go to without a
+        // way back
+        let dir = maze.rooms[room][tunnel].1;
+        maze.rooms[room][tunnel] = (room, dir);
+        prop_assert!(maze.validate().is_err());
+    }
+}
+
+#[test]
+fn unreachable_rooms_are_caught() {
+    // Two separate copies of the smallest cave, side by side
+    let mut maze = Maze::quad();
+    let second: Vec<_> = maze
+        .rooms
+        .iter()
+        .map(|room| {
+            let mut room = *room;
+            for tunnel in room.iter_mut() {
+                tunnel.0 += 4;
+            }
+            room
+        })
+        .collect();
+    maze.rooms.extend(second);
+
+    assert_eq!(maze.validate(), Err(MazeError::Unreachable { room: 4 }));
+}
+
+#[test]
+fn repeated_directions_are_caught() {
+    let mut maze = Maze::quad();
+    maze.rooms[0][1].1 = Direction::West;
+    assert_eq!(
+        maze.validate(),
+        Err(MazeError::RepeatedDirection {
+            room: 0,
+            direction: Direction::West
+        })
+    );
+}